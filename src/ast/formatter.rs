@@ -0,0 +1,618 @@
+use crate::{
+    constraints::ConstraintKind,
+    database::Database,
+    errors::DbErr,
+    field_value::FieldValue,
+    queries::{
+        expression_parser::{binding_power, BETWEEN_IN, IS, NOT, SIGN},
+        query_parser,
+    },
+    tokenizer::{Token, Tokenizer},
+};
+
+use super::{
+    BinaryOperator, ColumnConstraint, ColumnConstraintKind, ColumnDef, CreateTable, Expr, Literal, OrderByItem, SelectItem, Statement,
+    TableConstraint, TableConstraintKind, TableRef, UnaryOperator,
+};
+
+/// Renders syntax trees back to canonical SQL: upper case keywords, one column per line, and only the
+/// brackets and quotes that are needed for the text to parse back into the same tree.
+pub struct Formatter {
+    tokenizer: Tokenizer,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Formatter {
+        Formatter {
+            tokenizer: Tokenizer::new(),
+        }
+    }
+
+    pub fn format_statement(&self, statement: &Statement) -> String {
+        match statement {
+            Statement::CreateTable(create) => {
+                let columns = create.columns.iter().map(|column| {
+                    let mut definition = format!("    {} {}", self.quote_identifier(&column.name), column.data_type);
+                    for constraint in &column.constraints {
+                        definition.push(' ');
+                        definition.push_str(&self.format_column_constraint(constraint));
+                    }
+                    definition
+                });
+                let constraints = create
+                    .constraints
+                    .iter()
+                    .map(|constraint| format!("    {}", self.format_table_constraint(constraint)));
+                let elements: Vec<String> = columns.chain(constraints).collect();
+
+                format!(
+                    "CREATE TABLE {}{} (\n{}\n);",
+                    if create.if_not_exists { "IF NOT EXISTS " } else { "" },
+                    self.quote_identifier(&create.table.name),
+                    elements.join(",\n")
+                )
+            }
+            Statement::DeleteTable(delete) => format!(
+                "DROP TABLE {}{};",
+                if delete.if_exists { "IF EXISTS " } else { "" },
+                self.quote_identifier(&delete.table.name)
+            ),
+            Statement::Insert(insert) => {
+                let columns = match &insert.columns {
+                    Some(columns) => format!(" ({})", self.format_column_list(columns)),
+                    None => String::new(),
+                };
+                let rows: Vec<String> = insert
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let values: Vec<String> = row.iter().map(|value| self.format_expression(value)).collect();
+                        format!("    ({})", values.join(", "))
+                    })
+                    .collect();
+
+                format!(
+                    "INSERT INTO {}{} VALUES\n{};",
+                    self.quote_identifier(&insert.table.name),
+                    columns,
+                    rows.join(",\n")
+                )
+            }
+            Statement::Select(select) => {
+                let items: Vec<String> = select
+                    .projection
+                    .iter()
+                    .map(|item| match item {
+                        SelectItem::Wildcard => String::from("*"),
+                        SelectItem::Expr { expr, alias: None } => self.format_expression(expr),
+                        SelectItem::Expr { expr, alias: Some(alias) } => {
+                            format!("{} AS {}", self.format_expression(expr), self.quote_identifier(alias))
+                        }
+                    })
+                    .collect();
+
+                let mut clauses = vec![format!("SELECT {}", items.join(", "))];
+                if let Some(table) = &select.from {
+                    clauses.push(format!("FROM {}", self.quote_identifier(&table.name)));
+                }
+                if let Some(selection) = &select.selection {
+                    clauses.push(format!("WHERE {}", self.format_expression(selection)));
+                }
+                if !select.order_by.is_empty() {
+                    let keys: Vec<String> = select.order_by.iter().map(|item| self.format_order_by_item(item)).collect();
+                    clauses.push(format!("ORDER BY {}", keys.join(", ")));
+                }
+                if let Some(limit) = select.limit {
+                    clauses.push(format!("LIMIT {}", limit));
+                }
+                if let Some(offset) = select.offset {
+                    clauses.push(format!("OFFSET {}", offset));
+                }
+                format!("{};", clauses.join("\n"))
+            }
+            Statement::Update(update) => {
+                let assignments: Vec<String> = update
+                    .assignments
+                    .iter()
+                    .map(|assignment| {
+                        format!(
+                            "{} = {}",
+                            self.quote_identifier(&assignment.column),
+                            self.format_expression(&assignment.value)
+                        )
+                    })
+                    .collect();
+
+                let mut clauses = vec![
+                    format!("UPDATE {}", self.quote_identifier(&update.table.name)),
+                    format!("SET {}", assignments.join(", ")),
+                ];
+                if let Some(selection) = &update.selection {
+                    clauses.push(format!("WHERE {}", self.format_expression(selection)));
+                }
+                format!("{};", clauses.join("\n"))
+            }
+            Statement::Delete(delete) => {
+                let mut clauses = vec![format!("DELETE FROM {}", self.quote_identifier(&delete.table.name))];
+                if let Some(selection) = &delete.selection {
+                    clauses.push(format!("WHERE {}", self.format_expression(selection)));
+                }
+                format!("{};", clauses.join("\n"))
+            }
+        }
+    }
+
+    fn format_column_constraint(&self, constraint: &ColumnConstraint) -> String {
+        let kind = match &constraint.kind {
+            ColumnConstraintKind::NotNull => String::from("NOT NULL"),
+            ColumnConstraintKind::Default(expr) => format!("DEFAULT {}", self.format_expression(expr)),
+            ColumnConstraintKind::Unique => String::from("UNIQUE"),
+            ColumnConstraintKind::PrimaryKey => String::from("PRIMARY KEY"),
+            ColumnConstraintKind::Check(expr) => format!("CHECK ({})", self.format_expression(expr)),
+        };
+
+        self.name_constraint(&constraint.name, kind)
+    }
+
+    fn format_table_constraint(&self, constraint: &TableConstraint) -> String {
+        let kind = match &constraint.kind {
+            TableConstraintKind::PrimaryKey(columns) => format!("PRIMARY KEY ({})", self.format_column_list(columns)),
+            TableConstraintKind::Unique(columns) => format!("UNIQUE ({})", self.format_column_list(columns)),
+            TableConstraintKind::Check(expr) => format!("CHECK ({})", self.format_expression(expr)),
+        };
+
+        self.name_constraint(&constraint.name, kind)
+    }
+
+    /// `ASC` is left out, as it is the default
+    fn format_order_by_item(&self, item: &OrderByItem) -> String {
+        let mut key = self.format_expression(&item.expr);
+        if item.descending {
+            key.push_str(" DESC");
+        }
+        match item.nulls_first {
+            Some(true) => key.push_str(" NULLS FIRST"),
+            Some(false) => key.push_str(" NULLS LAST"),
+            None => {}
+        }
+        key
+    }
+
+    fn format_column_list(&self, columns: &[String]) -> String {
+        let columns: Vec<String> = columns.iter().map(|column| self.quote_identifier(column)).collect();
+        columns.join(", ")
+    }
+
+    fn name_constraint(&self, name: &Option<String>, kind: String) -> String {
+        match name {
+            Some(name) => format!("CONSTRAINT {} {}", self.quote_identifier(name), kind),
+            None => kind,
+        }
+    }
+
+    pub fn format_expression(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal(literal) => Formatter::format_literal(literal),
+            Expr::Parameter(idx) => format!("${}", idx + 1),
+            Expr::Column(column) => match &column.table {
+                Some(table) => format!("{}.{}", self.quote_identifier(table), self.quote_identifier(&column.column)),
+                None => self.quote_identifier(&column.column),
+            },
+            Expr::Unary { operator, operand } => match operator {
+                UnaryOperator::Not => format!("NOT {}", self.format_operand(operand, NOT)),
+                UnaryOperator::Plus | UnaryOperator::Minus => {
+                    let sign = if *operator == UnaryOperator::Plus { "+" } else { "-" };
+                    let operand = self.format_operand(operand, SIGN);
+
+                    // `- -1` must not become `--1`, which would start a comment
+                    if operand.starts_with(['+', '-']) {
+                        format!("{} {}", sign, operand)
+                    } else {
+                        format!("{}{}", sign, operand)
+                    }
+                }
+            },
+            Expr::Binary { left, operator, right } => {
+                let power = binding_power(*operator);
+                format!(
+                    "{} {} {}",
+                    self.format_operand(left, power),
+                    Formatter::operator_text(*operator),
+                    self.format_operand(right, power + 1)
+                )
+            }
+            Expr::IsNull { operand, negated } => {
+                format!("{} IS {}NULL", self.format_operand(operand, IS), if *negated { "NOT " } else { "" })
+            }
+            Expr::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => format!(
+                "{} {}BETWEEN {} AND {}",
+                self.format_operand(operand, BETWEEN_IN),
+                if *negated { "NOT " } else { "" },
+                self.format_operand(low, BETWEEN_IN + 1),
+                self.format_operand(high, BETWEEN_IN + 1)
+            ),
+            Expr::InList { operand, list, negated } => {
+                let list: Vec<String> = list.iter().map(|item| self.format_expression(item)).collect();
+                format!(
+                    "{} {}IN ({})",
+                    self.format_operand(operand, BETWEEN_IN),
+                    if *negated { "NOT " } else { "" },
+                    list.join(", ")
+                )
+            }
+        }
+    }
+
+    /// Formats an operand that has to bind at least as tightly as `min_power`, bracketing it if it doesn't
+    fn format_operand(&self, operand: &Expr, min_power: u8) -> String {
+        if Formatter::binding_power_of(operand) < min_power {
+            format!("({})", self.format_expression(operand))
+        } else {
+            self.format_expression(operand)
+        }
+    }
+
+    fn binding_power_of(expr: &Expr) -> u8 {
+        match expr {
+            Expr::Literal(_) | Expr::Column(_) | Expr::Parameter(_) => u8::MAX,
+            Expr::Unary {
+                operator: UnaryOperator::Not,
+                ..
+            } => NOT,
+            Expr::Unary { .. } => SIGN,
+            Expr::Binary { operator, .. } => binding_power(*operator),
+            Expr::IsNull { .. } => IS,
+            Expr::Between { .. } | Expr::InList { .. } => BETWEEN_IN,
+        }
+    }
+
+    pub(crate) fn operator_text(operator: BinaryOperator) -> &'static str {
+        match operator {
+            BinaryOperator::Or => "OR",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Equals => "=",
+            BinaryOperator::NotEquals => "<>",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Concat => "||",
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+        }
+    }
+
+    fn format_literal(literal: &Literal) -> String {
+        match literal {
+            Literal::Null => String::from("NULL"),
+            Literal::Boolean(true) => String::from("TRUE"),
+            Literal::Boolean(false) => String::from("FALSE"),
+            Literal::Integer(value) => value.to_string(),
+            // Debug output always keeps a decimal point or exponent, so the literal reads back as a float
+            Literal::Float(value) => format!("{:?}", value),
+            Literal::String(value) => format!("'{}'", value.replace('\'', "''")),
+            Literal::Blob(value) => FieldValue::Blob(value.clone()).to_string(),
+        }
+    }
+
+    /// Quotes an identifier unless it already reads back as that identifier, rather than a keyword
+    /// or several tokens
+    pub fn quote_identifier(&self, name: &str) -> String {
+        match self.tokenizer.tokenize(name) {
+            Ok(tokens) if tokens == [Token::Identifier(name.to_owned())] => name.to_owned(),
+            _ => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+}
+
+impl Database {
+    /// The `CREATE TABLE` statements that rebuild every table, ordered by table name
+    pub fn dump_schema(&self) -> String {
+        let formatter = Formatter::new();
+
+        let statements: Vec<String> = self
+            .table_names()
+            .into_iter()
+            .map(|name| {
+                let table = self.get_table(name).unwrap();
+                let columns = table.columns.iter().map(|column| {
+                    let mut constraints = Vec::new();
+                    if column.not_null {
+                        constraints.push(ColumnConstraintKind::NotNull);
+                    }
+                    if let Some(default) = &column.default {
+                        constraints.push(ColumnConstraintKind::Default(default.clone()));
+                    }
+
+                    ColumnDef {
+                        name: column.column_name.clone(),
+                        data_type: column.column_type,
+                        constraints: constraints.into_iter().map(|kind| ColumnConstraint { name: None, kind }).collect(),
+                    }
+                });
+                let constraints = table.constraints.iter().map(|constraint| {
+                    let column_names = |columns: &[usize]| columns.iter().map(|idx| table.columns[*idx].column_name.clone()).collect();
+
+                    TableConstraint {
+                        name: Some(constraint.name.clone()),
+                        kind: match &constraint.kind {
+                            ConstraintKind::PrimaryKey(index) => TableConstraintKind::PrimaryKey(column_names(&index.columns)),
+                            ConstraintKind::Unique(index) => TableConstraintKind::Unique(column_names(&index.columns)),
+                            ConstraintKind::Check(expr) => TableConstraintKind::Check(expr.clone()),
+                        },
+                    }
+                });
+
+                formatter.format_statement(&Statement::CreateTable(CreateTable {
+                    table: TableRef::new(name),
+                    columns: columns.collect(),
+                    constraints: constraints.collect(),
+                    if_not_exists: false,
+                }))
+            })
+            .collect();
+
+        statements.join("\n\n")
+    }
+
+    /// Parses every statement in the script, without executing any, and renders them as canonical SQL
+    pub fn format_script(&self, query: &str) -> Result<String, DbErr> {
+        let formatter = Formatter::new();
+        let statements = query_parser::parse_script(&self.registry(), query)?;
+
+        Ok(statements
+            .iter()
+            .map(|statement| formatter.format_statement(statement))
+            .collect::<Vec<String>>()
+            .join("\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{formatter::Formatter, Expr, Statement},
+        database::Database,
+        queries::{expression_parser::parse_expression, query_parser, statement_registry::StatementRegistry, token_cursor::TokenCursor},
+        tokenizer::Tokenizer,
+    };
+
+    fn parse_expr(expression: &str) -> Expr {
+        let tokens = Tokenizer::new().tokenize_spanned(expression).unwrap();
+        parse_expression(&mut TokenCursor::new(&tokens)).unwrap()
+    }
+
+    fn parse_statement(query: &str) -> Statement {
+        query_parser::parse_script(&StatementRegistry::new(), query).unwrap().remove(0)
+    }
+
+    /// Formats the expression, checks it parses back into the same tree, and returns the formatted text
+    fn round_trip_expression(expression: &str) -> String {
+        let formatter = Formatter::new();
+        let parsed = parse_expr(expression);
+        let formatted = formatter.format_expression(&parsed);
+
+        assert_eq!(parsed, parse_expr(&formatted), "{} was formatted as {}", expression, formatted);
+        assert_eq!(formatted, formatter.format_expression(&parse_expr(&formatted)));
+        formatted
+    }
+
+    fn round_trip_statement(query: &str) -> String {
+        let formatter = Formatter::new();
+        let parsed = parse_statement(query);
+        let formatted = formatter.format_statement(&parsed);
+
+        assert_eq!(parsed, parse_statement(&formatted), "{} was formatted as {}", query, formatted);
+        formatted
+    }
+
+    #[test]
+    fn format_create_table() {
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS users (\n    name TEXT,\n    code VARCHAR(10)\n);",
+            round_trip_statement("create table if not exists users (name string, code varchar( 10 ));")
+        );
+    }
+
+    #[test]
+    fn format_constraints() {
+        assert_eq!(
+            "CREATE TABLE t (\n    a INTEGER CONSTRAINT t_key PRIMARY KEY,\n    b TEXT NOT NULL DEFAULT 'x' || 'y' UNIQUE CHECK (b <> ''),\n    \
+             UNIQUE (a, \"Order\"),\n    CONSTRAINT check CHECK (a > 0)\n);",
+            round_trip_statement(
+                "create table t (a int constraint t_key primary key, b text not null default 'x' || 'y' unique check (b <> ''), \
+                 unique (a, `Order`), constraint `check` check (a > 0));"
+            )
+        );
+    }
+
+    #[test]
+    fn format_insert() {
+        assert_eq!(
+            "INSERT INTO t (a, \"b c\") VALUES\n    (1, 'x'),\n    (-2 * 3, NULL);",
+            round_trip_statement("insert into t (a, `b c`) values (1, 'x'), (-2 * 3, null);")
+        );
+        assert_eq!(
+            "INSERT INTO t VALUES\n    (TRUE);",
+            round_trip_statement("INSERT INTO t VALUES (true);")
+        );
+    }
+
+    #[test]
+    fn format_select() {
+        assert_eq!(
+            "SELECT *, a + 1 AS \"from\", b\nFROM t\nWHERE a IS NOT NULL;",
+            round_trip_statement("select *, a+1 as `from`, b from t where a is not null;")
+        );
+        assert_eq!("SELECT 1 AS one;", round_trip_statement("SELECT 1 one;"));
+    }
+
+    #[test]
+    fn format_order_and_limit() {
+        assert_eq!(
+            "SELECT a\nFROM t\nORDER BY a DESC NULLS LAST, b, c NULLS FIRST\nLIMIT 10\nOFFSET 20;",
+            round_trip_statement("select a from t order by a desc nulls last, b asc, c nulls first limit 10 offset 20;")
+        );
+        assert_eq!(
+            "SELECT *\nFROM t\nLIMIT 5\nOFFSET 1;",
+            round_trip_statement("SELECT * FROM t OFFSET 1 ROW FETCH NEXT 5 ROWS ONLY;")
+        );
+    }
+
+    #[test]
+    fn format_update() {
+        assert_eq!(
+            "UPDATE t\nSET a = a + 1, \"b c\" = NULL\nWHERE a < 10;",
+            round_trip_statement("update t set a = a + 1, `b c` = null where a < 10;")
+        );
+        assert_eq!("UPDATE t\nSET a = 1;", round_trip_statement("UPDATE t SET a = 1;"));
+        assert_eq!(
+            "UPDATE t\nSET a = $2\nWHERE b = $1;",
+            round_trip_statement("UPDATE t SET a = $2 WHERE b = $1;")
+        );
+    }
+
+    #[test]
+    fn format_delete() {
+        assert_eq!(
+            "DELETE FROM t\nWHERE a IN (1, 2);",
+            round_trip_statement("delete from t where a in (1,2);")
+        );
+        assert_eq!("DELETE FROM t;", round_trip_statement("DELETE FROM t;"));
+    }
+
+    #[test]
+    fn format_drop_table() {
+        assert_eq!("DROP TABLE users;", round_trip_statement("delete table users;"));
+        assert_eq!("DROP TABLE IF EXISTS users;", round_trip_statement("drop TABLE if exists users;"));
+    }
+
+    #[test]
+    fn quote_identifiers_that_need_it() {
+        let formatter = Formatter::new();
+
+        assert_eq!("users", formatter.quote_identifier("users"));
+        assert_eq!("CamelCase_2", formatter.quote_identifier("CamelCase_2"));
+        assert_eq!("\"Order Items\"", formatter.quote_identifier("Order Items"));
+        assert_eq!("\"table\"", formatter.quote_identifier("table"));
+        assert_eq!("\"2fast\"", formatter.quote_identifier("2fast"));
+        assert_eq!("\"x--\"", formatter.quote_identifier("x--"));
+        assert_eq!("\"say \"\"hi\"\"\"", formatter.quote_identifier("say \"hi\""));
+
+        assert_eq!(
+            "CREATE TABLE \"Order Items\" (\n    \"Order Date\" TEXT,\n    \"table\" INTEGER\n);",
+            round_trip_statement("CREATE TABLE `Order Items` (\"Order Date\" TEXT, `table` INT);")
+        );
+    }
+
+    #[test]
+    fn format_literals() {
+        assert_eq!("'it''s'", round_trip_expression("'it''s'"));
+        assert_eq!("1.5 + 1e20 + 0.5", round_trip_expression("1.5 + 1E20 + .5"));
+        assert_eq!("NULL OR TRUE OR FALSE", round_trip_expression("null or true or false"));
+        assert_eq!("31", round_trip_expression("0x1F"));
+        assert_eq!("X'0AFF' || X''", round_trip_expression("x'0aff' || X''"));
+    }
+
+    #[test]
+    fn format_only_needed_brackets() {
+        assert_eq!("1 + 2 * 3", round_trip_expression("(1 + (2 * 3))"));
+        assert_eq!("(1 + 2) * 3", round_trip_expression("(1 + 2) * 3"));
+        assert_eq!("1 - 2 - 3", round_trip_expression("(1 - 2) - 3"));
+        assert_eq!("1 - (2 - 3)", round_trip_expression("1 - (2 - 3)"));
+        assert_eq!("-(a + b)", round_trip_expression("-(a + b)"));
+        assert_eq!("- -1", round_trip_expression("-(-1)"));
+        assert_eq!("NOT (a OR b)", round_trip_expression("not (a or b)"));
+        assert_eq!("NOT a = 1", round_trip_expression("not (a = 1)"));
+        assert_eq!("(NOT a) = b", round_trip_expression("(not a) = b"));
+        assert_eq!("a = (NOT b)", round_trip_expression("a = not b"));
+        assert_eq!("a OR b AND c", round_trip_expression("a or (b and c)"));
+        assert_eq!("(a OR b) AND c", round_trip_expression("(a or b) and c"));
+    }
+
+    #[test]
+    fn format_special_forms() {
+        assert_eq!("t.a IS NOT NULL", round_trip_expression("t.a is not null"));
+        assert_eq!("(a OR b) IS NULL", round_trip_expression("(a or b) is null"));
+        assert_eq!("a + (b IS NULL)", round_trip_expression("a + (b is null)"));
+        assert_eq!(
+            "a NOT BETWEEN 1 AND (2 OR 3) AND b",
+            round_trip_expression("a not between 1 and (2 or 3) and b")
+        );
+        assert_eq!("a BETWEEN 1 AND 2 = b", round_trip_expression("(a between 1 and 2) = b"));
+        assert_eq!("a IN (1, 2 + 3, b OR c)", round_trip_expression("a in (1, 2 + 3, b or c)"));
+        assert_eq!("NOT a NOT IN ('x')", round_trip_expression("not a not in ('x')"));
+        assert_eq!(
+            "\"table\".\"Order Date\" || 'x'",
+            round_trip_expression("\"table\".`Order Date` || 'x'")
+        );
+    }
+
+    #[test]
+    fn dump_constraints() {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE t (id int PRIMARY KEY, a int NOT NULL DEFAULT -1 CHECK (a <> 0), b int, UNIQUE (a, b));",
+        )
+        .unwrap();
+
+        let schema = database.dump_schema();
+        assert_eq!(
+            "CREATE TABLE t (\n    id INTEGER,\n    a INTEGER NOT NULL DEFAULT -1,\n    b INTEGER,\n    \
+             CONSTRAINT t_pkey PRIMARY KEY (id),\n    CONSTRAINT t_a_check CHECK (a <> 0),\n    \
+             CONSTRAINT t_a_b_key UNIQUE (a, b)\n);",
+            schema
+        );
+
+        let mut restored = Database::new();
+        query_parser::execute_query(&mut restored, &schema).unwrap();
+        assert_eq!(schema, restored.dump_schema());
+    }
+
+    #[test]
+    fn dump_schema_as_ddl() {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE users (name string, age INTEGER); CREATE TABLE \"Order Items\" (price DOUBLE);",
+        )
+        .unwrap();
+
+        let schema = database.dump_schema();
+        assert_eq!(
+            "CREATE TABLE \"Order Items\" (\n    price DOUBLE\n);\n\nCREATE TABLE users (\n    name TEXT,\n    age INTEGER\n);",
+            schema
+        );
+
+        let mut restored = Database::new();
+        query_parser::execute_query(&mut restored, &schema).unwrap();
+        assert_eq!(schema, restored.dump_schema());
+    }
+
+    #[test]
+    fn format_scripts() {
+        let database = Database::new();
+        assert_eq!(
+            Ok(String::from("DROP TABLE IF EXISTS t;\n\nSELECT a\nFROM t\nWHERE a > 1;")),
+            database.format_script("drop table if exists t; select a from t where a>1;")
+        );
+        // Nothing runs, so the table doesn't have to exist
+        assert!(database.get_table("t").is_none());
+        assert!(database.format_script("select from;").is_err());
+    }
+}
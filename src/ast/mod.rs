@@ -0,0 +1,316 @@
+//! The typed syntax tree produced by the query parser. Queries are built from these nodes rather than
+//! from raw tokens, so each statement only has to be understood once.
+
+use crate::{data_type::DataType, field_value::FieldValue};
+
+pub mod formatter;
+
+/// A single parsed statement
+#[derive(Debug, PartialEq, Clone)]
+pub enum Statement {
+    CreateTable(CreateTable),
+    DeleteTable(DeleteTable),
+    Insert(Insert),
+    Select(Select),
+    Update(Update),
+    Delete(Delete),
+}
+
+/// `CREATE TABLE [IF NOT EXISTS] name (column type, ..., constraint, ...);`
+#[derive(Debug, PartialEq, Clone)]
+pub struct CreateTable {
+    pub table: TableRef,
+    pub columns: Vec<ColumnDef>,
+    pub constraints: Vec<TableConstraint>,
+    pub if_not_exists: bool,
+}
+
+/// `DELETE TABLE [IF EXISTS] name;`, or `DROP TABLE ...`
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeleteTable {
+    pub table: TableRef,
+    pub if_exists: bool,
+}
+
+/// `INSERT INTO name [(column, ...)] VALUES (value, ...), ...;`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Insert {
+    pub table: TableRef,
+    /// The columns each row gives values for, every column in order if none were listed
+    pub columns: Option<Vec<String>>,
+    pub rows: Vec<Vec<Expr>>,
+}
+
+/// `SELECT item, ... [FROM name] [WHERE condition] [ORDER BY key, ...] [LIMIT count] [OFFSET skipped];`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Select {
+    pub projection: Vec<SelectItem>,
+    /// Without a table, the items are evaluated once, as in `SELECT 1 + 1;`
+    pub from: Option<TableRef>,
+    pub selection: Option<Expr>,
+    pub order_by: Vec<OrderByItem>,
+    /// `LIMIT n` and `FETCH FIRST n ROWS ONLY` both end up here
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// One of the comma separated items after `SELECT`
+#[derive(Debug, PartialEq, Clone)]
+pub enum SelectItem {
+    /// `*`, every column of the table
+    Wildcard,
+    /// `expr [[AS] alias]`
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+/// `expr [ASC | DESC] [NULLS FIRST | NULLS LAST]` in an `ORDER BY`
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderByItem {
+    pub expr: Expr,
+    pub descending: bool,
+    /// Without `NULLS FIRST` or `NULLS LAST`, NULL sorts before every other value, so it comes first in
+    /// ascending order and last in descending order
+    pub nulls_first: Option<bool>,
+}
+
+impl OrderByItem {
+    pub(crate) fn nulls_first(&self) -> bool {
+        self.nulls_first.unwrap_or(!self.descending)
+    }
+}
+
+/// `UPDATE name SET column = value, ... [WHERE condition];`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Update {
+    pub table: TableRef,
+    pub assignments: Vec<Assignment>,
+    pub selection: Option<Expr>,
+}
+
+/// `column = value` in an `UPDATE`. The value is evaluated against the row as it was before the update.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assignment {
+    pub column: String,
+    pub value: Expr,
+}
+
+/// `DELETE FROM name [WHERE condition];`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Delete {
+    pub table: TableRef,
+    pub selection: Option<Expr>,
+}
+
+/// A column in a `CREATE TABLE` statement
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+/// A constraint written after a column's type, optionally named with `CONSTRAINT name`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnConstraint {
+    pub name: Option<String>,
+    pub kind: ColumnConstraintKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ColumnConstraintKind {
+    NotNull,
+    /// Not strictly a constraint, but written like one
+    Default(Expr),
+    Unique,
+    PrimaryKey,
+    Check(Expr),
+}
+
+/// A constraint listed among the columns of a table, optionally named with `CONSTRAINT name`
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableConstraint {
+    pub name: Option<String>,
+    pub kind: TableConstraintKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TableConstraintKind {
+    PrimaryKey(Vec<String>),
+    Unique(Vec<String>),
+    Check(Expr),
+}
+
+/// A table named by a statement
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableRef {
+    pub name: String,
+}
+
+impl TableRef {
+    pub fn new(name: &str) -> TableRef {
+        TableRef { name: name.to_owned() }
+    }
+}
+
+/// A column used in an expression, optionally qualified by its table as `table.column`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnRef {
+    pub table: Option<String>,
+    pub column: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum UnaryOperator {
+    Not,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    Concat,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Literal(Literal),
+    Column(ColumnRef),
+    /// A placeholder in a prepared statement, by the position of its argument counting from zero
+    Parameter(usize),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: BinaryOperator,
+        right: Box<Expr>,
+    },
+    /// `expr IS [NOT] NULL`
+    IsNull {
+        operand: Box<Expr>,
+        negated: bool,
+    },
+    /// `expr [NOT] BETWEEN low AND high`
+    Between {
+        operand: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    /// `expr [NOT] IN (item, ...)`
+    InList {
+        operand: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+}
+
+impl Expr {
+    pub fn column(column: &str) -> Expr {
+        Expr::Column(ColumnRef {
+            table: None,
+            column: column.to_owned(),
+        })
+    }
+
+    pub fn integer(value: i64) -> Expr {
+        Expr::Literal(Literal::Integer(value))
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expr) -> Expr {
+        Expr::Unary {
+            operator,
+            operand: Box::new(operand),
+        }
+    }
+
+    pub fn binary(left: Expr, operator: BinaryOperator, right: Expr) -> Expr {
+        Expr::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    /// Every column the expression refers to, in the order they are written
+    pub fn columns(&self) -> Vec<&ColumnRef> {
+        match self {
+            Expr::Literal(_) | Expr::Parameter(_) => Vec::new(),
+            Expr::Column(column) => vec![column],
+            Expr::Unary { operand, .. } | Expr::IsNull { operand, .. } => operand.columns(),
+            Expr::Binary { left, right, .. } => [left.columns(), right.columns()].concat(),
+            Expr::Between { operand, low, high, .. } => [operand.columns(), low.columns(), high.columns()].concat(),
+            Expr::InList { operand, list, .. } => {
+                let mut columns = operand.columns();
+                columns.extend(list.iter().flat_map(Expr::columns));
+                columns
+            }
+        }
+    }
+
+    /// The expression with every parameter replaced by its argument
+    pub fn bind(&self, arguments: &[FieldValue]) -> Expr {
+        let bind = |expr: &Expr| Box::new(expr.bind(arguments));
+
+        match self {
+            Expr::Parameter(idx) => match arguments.get(*idx) {
+                Some(argument) => Expr::Literal(Literal::from(argument)),
+                None => self.clone(),
+            },
+            Expr::Literal(_) | Expr::Column(_) => self.clone(),
+            Expr::Unary { operator, operand } => Expr::Unary {
+                operator: *operator,
+                operand: bind(operand),
+            },
+            Expr::Binary { left, operator, right } => Expr::Binary {
+                left: bind(left),
+                operator: *operator,
+                right: bind(right),
+            },
+            Expr::IsNull { operand, negated } => Expr::IsNull {
+                operand: bind(operand),
+                negated: *negated,
+            },
+            Expr::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => Expr::Between {
+                operand: bind(operand),
+                low: bind(low),
+                high: bind(high),
+                negated: *negated,
+            },
+            Expr::InList { operand, list, negated } => Expr::InList {
+                operand: bind(operand),
+                list: list.iter().map(|item| item.bind(arguments)).collect(),
+                negated: *negated,
+            },
+        }
+    }
+}
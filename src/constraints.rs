@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::Expr,
+    database::{ColumnDescription, Table},
+    errors::DbErr,
+    evaluator::Evaluator,
+    field_value::FieldValue,
+    row_store::RowId,
+};
+
+/// A rule that every row of a table has to follow. Constraints written on a single column are stored the same
+/// way as those written for the whole table.
+pub(crate) struct Constraint {
+    pub name: String,
+    pub kind: ConstraintKind,
+}
+
+pub(crate) enum ConstraintKind {
+    PrimaryKey(UniqueIndex),
+    Unique(UniqueIndex),
+    Check(Expr),
+}
+
+/// Finds the row holding a combination of values in some columns, so duplicates are found without a scan.
+/// Rows with a NULL in any of the columns are left out, as NULL never equals another NULL.
+pub(crate) struct UniqueIndex {
+    pub columns: Vec<usize>,
+    rows: HashMap<Vec<FieldValue>, RowId>,
+}
+
+impl UniqueIndex {
+    pub(crate) fn new(columns: Vec<usize>) -> UniqueIndex {
+        UniqueIndex {
+            columns,
+            rows: HashMap::new(),
+        }
+    }
+
+    fn key(&self, values: &[FieldValue]) -> Option<Vec<FieldValue>> {
+        let key: Vec<FieldValue> = self.columns.iter().map(|idx| values[*idx].clone()).collect();
+        if key.iter().any(FieldValue::is_null) {
+            None
+        } else {
+            Some(key)
+        }
+    }
+
+    /// Whether a row other than `replacing` already holds the same values
+    fn conflicts(&self, values: &[FieldValue], replacing: Option<RowId>) -> bool {
+        self.key(values)
+            .and_then(|key| self.rows.get(&key))
+            .is_some_and(|row_id| Some(*row_id) != replacing)
+    }
+
+    fn insert(&mut self, values: &[FieldValue], row_id: RowId) {
+        if let Some(key) = self.key(values) {
+            self.rows.insert(key, row_id);
+        }
+    }
+
+    fn remove(&mut self, values: &[FieldValue]) {
+        if let Some(key) = self.key(values) {
+            self.rows.remove(&key);
+        }
+    }
+}
+
+impl Constraint {
+    fn index_mut(&mut self) -> Option<&mut UniqueIndex> {
+        match &mut self.kind {
+            ConstraintKind::PrimaryKey(index) | ConstraintKind::Unique(index) => Some(index),
+            ConstraintKind::Check(_) => None,
+        }
+    }
+}
+
+impl Table {
+    /// Checks a row against the table's columns and every constraint, without changing anything.
+    /// `replacing` is the row being updated, which the new values may of course duplicate.
+    pub(crate) fn validate_row(&self, values: &[FieldValue], replacing: Option<RowId>) -> Result<(), DbErr> {
+        if values.len() != self.columns.len() {
+            return Err(DbErr::Generic(format!(
+                "Expected {} values, got {}",
+                self.columns.len(),
+                values.len()
+            )));
+        }
+
+        for (idx, column) in self.columns.iter().enumerate() {
+            if values[idx].is_null() && self.is_not_null(idx) {
+                return Err(DbErr::NotNullViolation {
+                    column: column.column_name.clone(),
+                });
+            }
+        }
+
+        let evaluator = Evaluator::new(None, &self.columns);
+        for constraint in &self.constraints {
+            let violated = match &constraint.kind {
+                ConstraintKind::PrimaryKey(index) | ConstraintKind::Unique(index) => index.conflicts(values, replacing),
+                // Only FALSE violates a check, an unknown result passes
+                ConstraintKind::Check(expr) => evaluator.evaluate(expr, values)?.truth()? == Some(false),
+            };
+
+            if violated {
+                let constraint_name = constraint.name.clone();
+                return Err(match constraint.kind {
+                    ConstraintKind::PrimaryKey(_) => DbErr::PrimaryKeyViolation {
+                        constraint: constraint_name,
+                    },
+                    ConstraintKind::Unique(_) => DbErr::UniqueViolation {
+                        constraint: constraint_name,
+                    },
+                    ConstraintKind::Check(_) => DbErr::CheckViolation {
+                        constraint: constraint_name,
+                    },
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the column was declared `NOT NULL`, or is part of the primary key
+    pub(crate) fn is_not_null(&self, column_idx: usize) -> bool {
+        self.columns[column_idx].not_null
+            || self.constraints.iter().any(|constraint| match &constraint.kind {
+                ConstraintKind::PrimaryKey(index) => index.columns.contains(&column_idx),
+                _ => false,
+            })
+    }
+
+    /// Adds a row to every unique index
+    pub(crate) fn index_row(&mut self, row_id: RowId) {
+        if let Some(entry) = self.rows.get(row_id) {
+            for index in self.constraints.iter_mut().filter_map(Constraint::index_mut) {
+                index.insert(&entry.fields, row_id);
+            }
+        }
+    }
+
+    /// Removes a row from every unique index
+    pub(crate) fn unindex_row(&mut self, row_id: RowId) {
+        if let Some(entry) = self.rows.get(row_id) {
+            for index in self.constraints.iter_mut().filter_map(Constraint::index_mut) {
+                index.remove(&entry.fields);
+            }
+        }
+    }
+}
+
+/// Builds the unique index for the named columns, failing if any of them doesn't exist
+pub(crate) fn index_columns(columns: &[ColumnDescription], names: &[String]) -> Result<UniqueIndex, DbErr> {
+    let indexes = names
+        .iter()
+        .map(|name| {
+            columns
+                .iter()
+                .position(|column| column.column_name == *name)
+                .ok_or_else(|| DbErr::ColumnNotExists(name.clone()))
+        })
+        .collect::<Result<Vec<usize>, DbErr>>()?;
+
+    Ok(UniqueIndex::new(indexes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::{Database, Table},
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::query_parser,
+    };
+
+    fn with_table(definition: &str, test: impl FnOnce(&mut Table)) {
+        let mut database = Database::new();
+        query_parser::execute_query(&mut database, &format!("CREATE TABLE t ({});", definition)).unwrap();
+        test(database.get_table_mut("t").unwrap());
+    }
+
+    fn row(values: &[Option<i64>]) -> Vec<FieldValue> {
+        values
+            .iter()
+            .map(|value| value.map_or(FieldValue::Null, FieldValue::Integer))
+            .collect()
+    }
+
+    #[test]
+    fn not_null() {
+        with_table("a int NOT NULL, b int", |table| {
+            let row_id = table.insert_row(row(&[Some(1), None])).unwrap();
+            assert_eq!(
+                Err(DbErr::NotNullViolation { column: String::from("a") }),
+                table.insert_row(row(&[None, Some(1)]))
+            );
+            assert_eq!(
+                Err(DbErr::NotNullViolation { column: String::from("a") }),
+                table.update_row(row_id, row(&[None, None]))
+            );
+            assert_eq!(1, table.rows.len());
+        });
+    }
+
+    #[test]
+    fn unique_allows_many_nulls() {
+        with_table("a int UNIQUE", |table| {
+            assert!(table.insert_row(row(&[Some(1)])).is_ok());
+            assert!(table.insert_row(row(&[None])).is_ok());
+            assert!(table.insert_row(row(&[None])).is_ok());
+            assert_eq!(
+                Err(DbErr::UniqueViolation {
+                    constraint: String::from("t_a_key")
+                }),
+                table.insert_row(row(&[Some(1)]))
+            );
+        });
+    }
+
+    #[test]
+    fn multi_column_primary_key() {
+        with_table("a int, b int, PRIMARY KEY (a, b)", |table| {
+            assert!(table.insert_row(row(&[Some(1), Some(1)])).is_ok());
+            assert!(table.insert_row(row(&[Some(1), Some(2)])).is_ok());
+            assert_eq!(
+                Err(DbErr::PrimaryKeyViolation {
+                    constraint: String::from("t_pkey")
+                }),
+                table.insert_row(row(&[Some(1), Some(2)]))
+            );
+            // Primary key columns are implicitly NOT NULL
+            assert_eq!(
+                Err(DbErr::NotNullViolation { column: String::from("b") }),
+                table.insert_row(row(&[Some(2), None]))
+            );
+        });
+    }
+
+    #[test]
+    fn check_fails_only_on_false() {
+        with_table("a int CONSTRAINT positive CHECK (a > 0)", |table| {
+            assert!(table.insert_row(row(&[Some(1)])).is_ok());
+            assert!(table.insert_row(row(&[None])).is_ok());
+            assert_eq!(
+                Err(DbErr::CheckViolation {
+                    constraint: String::from("positive")
+                }),
+                table.insert_row(row(&[Some(0)]))
+            );
+        });
+    }
+
+    #[test]
+    fn updates_keep_indexes_current() {
+        with_table("a int PRIMARY KEY", |table| {
+            let first = table.insert_row(row(&[Some(1)])).unwrap();
+            let second = table.insert_row(row(&[Some(2)])).unwrap();
+
+            // A row may keep its own key
+            assert!(table.update_row(first, row(&[Some(1)])).is_ok());
+            assert!(table.update_row(second, row(&[Some(1)])).is_err());
+
+            assert!(table.update_row(first, row(&[Some(3)])).is_ok());
+            assert!(table.insert_row(row(&[Some(1)])).is_ok());
+            assert!(table.insert_row(row(&[Some(3)])).is_err());
+
+            assert!(table.delete_row(first).is_some());
+            assert!(table.insert_row(row(&[Some(3)])).is_ok());
+            assert!(table.update_row(second, row(&[Some(1)])).is_err());
+            assert!(table.update_row(first, row(&[Some(4)])).is_err());
+            assert!(table.delete_row(first).is_none());
+        });
+    }
+}
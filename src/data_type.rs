@@ -0,0 +1,349 @@
+use std::fmt;
+
+use crate::{errors::DbErr, field_value::FieldValue};
+
+/// The type of a column. Every type name that `CREATE TABLE` accepts resolves to one of these.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DataType {
+    SmallInt,
+    Integer,
+    BigInt,
+    Real,
+    Double,
+    Boolean,
+    Text,
+    /// Text of at most this many characters
+    Varchar(u32),
+    Blob,
+}
+
+/// Other names accepted for each type, along with the canonical name they stand for
+const SYNONYMS: &[(&str, &str)] = &[
+    ("INT2", "SMALLINT"),
+    ("INT", "INTEGER"),
+    ("INT4", "INTEGER"),
+    ("INT8", "BIGINT"),
+    ("FLOAT4", "REAL"),
+    ("FLOAT", "DOUBLE"),
+    ("FLOAT8", "DOUBLE"),
+    ("BOOL", "BOOLEAN"),
+    ("STRING", "TEXT"),
+    ("BYTEA", "BLOB"),
+];
+
+/// The canonical type names, in the order they are suggested
+const CANONICAL_NAMES: &[&str] = &[
+    "SMALLINT", "INTEGER", "BIGINT", "REAL", "DOUBLE", "BOOLEAN", "TEXT", "VARCHAR", "BLOB",
+];
+
+impl DataType {
+    /// Resolves a type as written in a column definition, such as `int` or `VARCHAR(20)`
+    pub(crate) fn from_name(name: &str, parameters: &[i64]) -> Result<DataType, DbErr> {
+        let upper = name.to_uppercase();
+        let canonical = SYNONYMS
+            .iter()
+            .find(|(synonym, _)| *synonym == upper)
+            .map_or(upper.as_str(), |(_, canonical)| canonical);
+
+        let data_type = match canonical {
+            "SMALLINT" => DataType::SmallInt,
+            "INTEGER" => DataType::Integer,
+            "BIGINT" => DataType::BigInt,
+            "REAL" => DataType::Real,
+            "DOUBLE" => DataType::Double,
+            "BOOLEAN" => DataType::Boolean,
+            "TEXT" => DataType::Text,
+            "BLOB" => DataType::Blob,
+            "VARCHAR" => {
+                return match parameters {
+                    [length] if *length < 1 => Err(DbErr::InvalidDataType(format!("VARCHAR length must be positive, got {}", length))),
+                    [length] => match u32::try_from(*length) {
+                        Ok(length) => Ok(DataType::Varchar(length)),
+                        Err(_) => Err(DbErr::InvalidDataType(format!(
+                            "VARCHAR length can't be more than {}, got {}",
+                            u32::MAX,
+                            length
+                        ))),
+                    },
+                    _ => Err(DbErr::InvalidDataType(String::from(
+                        "VARCHAR takes a single length, such as VARCHAR(255)",
+                    ))),
+                }
+            }
+            _ => return Err(DbErr::InvalidDataType(unknown_type_message(name))),
+        };
+
+        if !parameters.is_empty() {
+            return Err(DbErr::InvalidDataType(format!("{} doesn't take any parameters", data_type)));
+        }
+
+        Ok(data_type)
+    }
+
+    /// Converts a value for storage in a column of this type, such as an integer going into a DOUBLE column.
+    /// NULL fits every type. Integers are stored exactly or not at all, so one out of range for an integer type,
+    /// or one a REAL or DOUBLE can't represent exactly, is rejected. Doubles going into a REAL column are rounded
+    /// to single precision, as that is all a REAL stores.
+    pub(crate) fn coerce(self, value: FieldValue, column_name: &str) -> Result<FieldValue, DbErr> {
+        let out_of_range =
+            |value: FieldValue| DbErr::ArithmeticError(format!("{} is out of range for {} column {}", value, self, column_name));
+
+        let coerced = match (self, value) {
+            (_, FieldValue::Null) => FieldValue::Null,
+            (DataType::SmallInt | DataType::Integer | DataType::BigInt, value) => {
+                let integer = match value {
+                    FieldValue::Integer(integer) => integer,
+                    // Doubles without a fractional part are stored as the integer they equal
+                    FieldValue::Double(double) if double.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&double) => {
+                        double as i64
+                    }
+                    FieldValue::Double(double) if double.is_finite() && double.fract() == 0.0 => {
+                        return Err(out_of_range(FieldValue::Double(double)))
+                    }
+                    value => return Err(self.mismatch(&value, column_name)),
+                };
+
+                let fits = match self {
+                    DataType::SmallInt => i16::try_from(integer).is_ok(),
+                    DataType::Integer => i32::try_from(integer).is_ok(),
+                    _ => true,
+                };
+                if !fits {
+                    return Err(out_of_range(FieldValue::Integer(integer)));
+                }
+                FieldValue::Integer(integer)
+            }
+            (DataType::Real | DataType::Double, value) => {
+                let double = match value {
+                    // Above 2^53 for a DOUBLE, or 2^24 for a REAL, not every integer has a floating point twin
+                    FieldValue::Integer(integer) => {
+                        let exact = match self {
+                            DataType::Real => (integer as f32) as i128 == integer as i128,
+                            _ => (integer as f64) as i128 == integer as i128,
+                        };
+                        if !exact {
+                            return Err(DbErr::ArithmeticError(format!(
+                                "{} can't be stored exactly in {} column {}",
+                                integer, self, column_name
+                            )));
+                        }
+                        integer as f64
+                    }
+                    FieldValue::Double(double) => double,
+                    value => return Err(self.mismatch(&value, column_name)),
+                };
+
+                if self == DataType::Real {
+                    // Rounded to single precision, the way it would be stored
+                    let single = double as f32;
+                    if single.is_infinite() && double.is_finite() {
+                        return Err(out_of_range(FieldValue::Double(double)));
+                    }
+                    FieldValue::Double(single as f64)
+                } else {
+                    FieldValue::Double(double)
+                }
+            }
+            (DataType::Boolean, value @ FieldValue::Boolean(_)) => value,
+            (DataType::Text, value @ FieldValue::Text(_)) => value,
+            (DataType::Varchar(length), FieldValue::Text(text)) => {
+                if text.chars().count() > length as usize {
+                    return Err(DbErr::TypeMismatch(format!(
+                        "Value is too long for {} column {}",
+                        self, column_name
+                    )));
+                }
+                FieldValue::Text(text)
+            }
+            (DataType::Blob, value @ FieldValue::Blob(_)) => value,
+            (_, value) => return Err(self.mismatch(&value, column_name)),
+        };
+
+        Ok(coerced)
+    }
+
+    fn mismatch(self, value: &FieldValue, column_name: &str) -> DbErr {
+        DbErr::TypeMismatch(format!("Can't store {} in {} column {}", value.type_name(), self, column_name))
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::SmallInt => write!(f, "SMALLINT"),
+            DataType::Integer => write!(f, "INTEGER"),
+            DataType::BigInt => write!(f, "BIGINT"),
+            DataType::Real => write!(f, "REAL"),
+            DataType::Double => write!(f, "DOUBLE"),
+            DataType::Boolean => write!(f, "BOOLEAN"),
+            DataType::Text => write!(f, "TEXT"),
+            DataType::Varchar(length) => write!(f, "VARCHAR({})", length),
+            DataType::Blob => write!(f, "BLOB"),
+        }
+    }
+}
+
+/// Names the type that was probably meant if `name` is a small typo away from one, and lists every type otherwise
+fn unknown_type_message(name: &str) -> String {
+    let upper = name.to_uppercase();
+    let closest = CANONICAL_NAMES
+        .iter()
+        .copied()
+        .chain(SYNONYMS.iter().map(|(synonym, _)| *synonym))
+        .map(|candidate| (edit_distance(&upper, candidate), candidate))
+        .min();
+
+    match closest {
+        Some((distance, candidate)) if distance <= 2 && distance < upper.len() => {
+            format!("Unknown data type {}, did you mean {}?", name, candidate)
+        }
+        _ => format!("Unknown data type {}, expected one of {}", name, CANONICAL_NAMES.join(", ")),
+    }
+}
+
+/// The number of single character insertions, deletions and substitutions that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data_type::DataType, errors::DbErr, field_value::FieldValue};
+
+    #[test]
+    fn canonical_names_and_synonyms() {
+        assert_eq!(Ok(DataType::Integer), DataType::from_name("integer", &[]));
+        assert_eq!(Ok(DataType::Integer), DataType::from_name("INT", &[]));
+        assert_eq!(Ok(DataType::BigInt), DataType::from_name("int8", &[]));
+        assert_eq!(Ok(DataType::Double), DataType::from_name("Float", &[]));
+        assert_eq!(Ok(DataType::Boolean), DataType::from_name("BOOL", &[]));
+        assert_eq!(Ok(DataType::Text), DataType::from_name("string", &[]));
+        assert_eq!(Ok(DataType::Varchar(20)), DataType::from_name("varchar", &[20]));
+        assert_eq!(Ok(DataType::Blob), DataType::from_name("BYTEA", &[]));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for data_type in [DataType::SmallInt, DataType::Real, DataType::Text, DataType::Varchar(255)] {
+            let parameters: &[i64] = match data_type {
+                DataType::Varchar(length) => &[length as i64],
+                _ => &[],
+            };
+            let name = data_type.to_string();
+            let name = name.split('(').next().unwrap();
+
+            assert_eq!(Ok(data_type), DataType::from_name(name, parameters));
+        }
+    }
+
+    #[test]
+    fn invalid_types() {
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from(
+                "Unknown data type banana, expected one of SMALLINT, INTEGER, BIGINT, REAL, DOUBLE, BOOLEAN, TEXT, VARCHAR, BLOB"
+            ))),
+            DataType::from_name("banana", &[])
+        );
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from(
+                "Unknown data type VARCHR, did you mean VARCHAR?"
+            ))),
+            DataType::from_name("VARCHR", &[5])
+        );
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from("INTEGER doesn't take any parameters"))),
+            DataType::from_name("int", &[4])
+        );
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from(
+                "VARCHAR takes a single length, such as VARCHAR(255)"
+            ))),
+            DataType::from_name("VARCHAR", &[])
+        );
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from("VARCHAR length must be positive, got 0"))),
+            DataType::from_name("VARCHAR", &[0])
+        );
+        assert_eq!(Ok(DataType::Varchar(u32::MAX)), DataType::from_name("VARCHAR", &[u32::MAX as i64]));
+        assert_eq!(
+            Err(DbErr::InvalidDataType(String::from(
+                "VARCHAR length can't be more than 4294967295, got 5000000000"
+            ))),
+            DataType::from_name("VARCHAR", &[5_000_000_000])
+        );
+    }
+
+    #[test]
+    fn coerce_values() {
+        assert_eq!(Ok(FieldValue::Double(2.0)), DataType::Double.coerce(FieldValue::Integer(2), "a"));
+        assert_eq!(Ok(FieldValue::Integer(2)), DataType::Integer.coerce(FieldValue::Double(2.0), "a"));
+        assert_eq!(
+            Ok(FieldValue::Double(0.1_f32 as f64)),
+            DataType::Real.coerce(FieldValue::Double(0.1), "a")
+        );
+        assert_eq!(Ok(FieldValue::Null), DataType::Boolean.coerce(FieldValue::Null, "a"));
+        assert_eq!(
+            Ok(FieldValue::Text(String::from("héllo"))),
+            DataType::Varchar(5).coerce(FieldValue::Text(String::from("héllo")), "a")
+        );
+
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't store DOUBLE in INTEGER column a"))),
+            DataType::Integer.coerce(FieldValue::Double(2.5), "a")
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from("40000 is out of range for SMALLINT column a"))),
+            DataType::SmallInt.coerce(FieldValue::Integer(40000), "a")
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from("1e300 is out of range for REAL column a"))),
+            DataType::Real.coerce(FieldValue::Double(1e300), "a")
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from(
+                "9007199254740993 can't be stored exactly in DOUBLE column a"
+            ))),
+            DataType::Double.coerce(FieldValue::Integer(9_007_199_254_740_993), "a")
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from(
+                "16777217 can't be stored exactly in REAL column a"
+            ))),
+            DataType::Real.coerce(FieldValue::Integer(16_777_217), "a")
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from(
+                "9223372036854775807 can't be stored exactly in DOUBLE column a"
+            ))),
+            DataType::Double.coerce(FieldValue::Integer(i64::MAX), "a")
+        );
+        assert_eq!(
+            Ok(FieldValue::Double(9_007_199_254_740_992.0)),
+            DataType::Double.coerce(FieldValue::Integer(9_007_199_254_740_992), "a")
+        );
+        assert_eq!(
+            Ok(FieldValue::Double(16_777_216.0)),
+            DataType::Real.coerce(FieldValue::Integer(16_777_216), "a")
+        );
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't store INTEGER in TEXT column a"))),
+            DataType::Text.coerce(FieldValue::Integer(1), "a")
+        );
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Value is too long for VARCHAR(2) column a"))),
+            DataType::Varchar(2).coerce(FieldValue::Text(String::from("abc")), "a")
+        );
+    }
+}
@@ -1,43 +1,93 @@
-use std::{borrow::BorrowMut, collections::HashMap};
-
-use crate::errors::DbErr;
-
-pub enum FieldValue {
-    i32(i32),
-}
-
-struct TableEntry {
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    ast::Expr,
+    constraints::Constraint,
+    data_type::DataType,
+    errors::DbErr,
+    field_value::FieldValue,
+    queries::statement_registry::StatementRegistry,
+    row_store::{RowId, RowStore},
+};
+
+pub struct TableEntry {
     pub fields: Vec<FieldValue>,
 }
 
 pub struct Table {
     pub columns: Vec<ColumnDescription>,
-    pub rows: Vec<TableEntry>,
+    pub rows: RowStore,
+    pub(crate) constraints: Vec<Constraint>,
 }
 
 pub struct Database {
     tables: HashMap<String, Table>,
+    /// Shared with the queries being run, so they can execute against the database while it is borrowed
+    pub(crate) registry: Rc<StatementRegistry>,
 }
 
 pub struct ColumnDescription {
     pub column_name: String,
-    pub column_type: String,
+    pub column_type: DataType,
+    pub not_null: bool,
+    /// Evaluated for every row that doesn't give the column a value
+    pub(crate) default: Option<Expr>,
+}
+
+impl ColumnDescription {
+    pub fn new(column_name: &str, column_type: DataType) -> ColumnDescription {
+        ColumnDescription {
+            column_name: column_name.to_owned(),
+            column_type,
+            not_null: false,
+            default: None,
+        }
+    }
+}
+
+impl Default for Database {
+    fn default() -> Database {
+        Database::new()
+    }
 }
 
 impl Database {
     pub fn new() -> Database {
-        Database { tables: HashMap::new() }
+        Database {
+            tables: HashMap::new(),
+            registry: Rc::new(StatementRegistry::new()),
+        }
     }
 
     pub(crate) fn get_table(&self, name: &str) -> Option<&Table> {
         self.tables.get(name)
     }
 
-    pub(crate) fn create_table(&mut self, name: &str, columns: Vec<ColumnDescription>) -> Result<&Table, DbErr> {
+    pub(crate) fn get_table_mut(&mut self, name: &str) -> Option<&mut Table> {
+        self.tables.get_mut(name)
+    }
+
+    /// The names of every table, in order
+    pub(crate) fn table_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.tables.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn create_table(&mut self, name: &str, columns: Vec<ColumnDescription>) -> Result<&Table, DbErr> {
+        self.create_table_with_constraints(name, columns, Vec::new())
+    }
+
+    pub(crate) fn create_table_with_constraints(
+        &mut self,
+        name: &str,
+        columns: Vec<ColumnDescription>,
+        constraints: Vec<Constraint>,
+    ) -> Result<&Table, DbErr> {
         match self.tables.contains_key(name) {
             true => Err(DbErr::TableAlreadyExists),
             false => {
-                let table = Table::from_column_definition(columns);
+                let table = Table::new(columns, constraints);
                 self.tables.insert(name.to_string(), table);
                 Ok(self.tables.get(name).unwrap())
             }
@@ -60,15 +110,50 @@ impl TableEntry {
 
 impl Table {
     pub fn from_column_definition(columns: Vec<ColumnDescription>) -> Table {
-        return Table { columns, rows: Vec::new() };
+        Table::new(columns, Vec::new())
+    }
+
+    pub(crate) fn new(columns: Vec<ColumnDescription>, constraints: Vec<Constraint>) -> Table {
+        Table {
+            columns,
+            rows: RowStore::new(),
+            constraints,
+        }
+    }
+
+    /// Adds a row after checking it against every constraint, returning the id it keeps until it is deleted
+    pub(crate) fn insert_row(&mut self, values: Vec<FieldValue>) -> Result<RowId, DbErr> {
+        self.validate_row(&values, None)?;
+
+        let row_id = self.rows.insert(TableEntry { fields: values });
+        self.index_row(row_id);
+        Ok(row_id)
     }
 
-    fn insert_row(&mut self, values: Vec<FieldValue>) {
-        self.rows.push(TableEntry { fields: values });
+    /// Replaces every value of a row, after checking the new values against every constraint
+    pub(crate) fn update_row(&mut self, row_id: RowId, values: Vec<FieldValue>) -> Result<(), DbErr> {
+        if self.rows.get(row_id).is_none() {
+            return Err(DbErr::Generic(format!("Row {:?} doesn't exist", row_id)));
+        }
+        self.validate_row(&values, Some(row_id))?;
+
+        self.replace_row(row_id, values);
+        Ok(())
+    }
+
+    /// Replaces every value of a row without checking any constraint, such as to undo an update
+    pub(crate) fn replace_row(&mut self, row_id: RowId, values: Vec<FieldValue>) {
+        self.unindex_row(row_id);
+        if let Some(entry) = self.rows.get_mut(row_id) {
+            entry.fields = values;
+        }
+        self.index_row(row_id);
     }
 
-    fn delete_row(&mut self, row_id: usize) {
-        self.rows.remove(row_id);
+    /// Removes a row, returning it unless it had already been deleted. The ids of the other rows stay valid.
+    pub(crate) fn delete_row(&mut self, row_id: RowId) -> Option<TableEntry> {
+        self.unindex_row(row_id);
+        self.rows.remove(row_id)
     }
 }
 
@@ -1,6 +1,151 @@
+use std::fmt;
+
+use crate::tokenizer::Span;
+
 #[derive(PartialEq, Eq, Debug, Clone)]
-pub(crate) enum DbErr {
+pub enum DbErr {
     Generic(String),
+    Syntax(SyntaxError),
     TableAlreadyExists,
     TableNotExists,
+    /// A column type that doesn't exist, or was given the wrong parameters
+    InvalidDataType(String),
+    /// A value of the wrong kind for an operation, such as comparing text with a number
+    TypeMismatch(String),
+    /// Division by zero, or an integer result out of range
+    ArithmeticError(String),
+    ColumnNotExists(String),
+    /// A constraint that can't be created, such as a second primary key
+    InvalidConstraint(String),
+    NotNullViolation {
+        column: String,
+    },
+    UniqueViolation {
+        constraint: String,
+    },
+    PrimaryKeyViolation {
+        constraint: String,
+    },
+    CheckViolation {
+        constraint: String,
+    },
+    ParameterCountMismatch {
+        expected: usize,
+        provided: usize,
+    },
+}
+
+/// An error tied to a location in the query text.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SyntaxError {
+    pub message: String,
+    pub span: Span,
+    /// The text ended while something was still open, such as a string, so more text could still make it valid
+    pub incomplete: bool,
+}
+
+impl SyntaxError {
+    pub fn new(message: String, span: Span) -> SyntaxError {
+        SyntaxError {
+            message,
+            span,
+            incomplete: false,
+        }
+    }
+
+    /// Marks the error as caused by the text ending too early
+    pub fn incomplete(mut self) -> SyntaxError {
+        self.incomplete = true;
+        self
+    }
+
+    /// Renders the error followed by the offending line of `query`, with carets under the span:
+    ///
+    /// ```text
+    /// Unexpected token at 1:14
+    /// CREATE TABLE (name string);
+    ///              ^
+    /// ```
+    pub fn render(&self, query: &str) -> String {
+        let line_start = query[..self.span.start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = query[line_start..].find('\n').map_or(query.len(), |idx| line_start + idx);
+        let line = query[line_start..line_end].trim_end_matches('\r');
+
+        // Keep tabs in the padding so the caret lines up with the original text
+        let padding: String = query[line_start..self.span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined = query[self.span.start..self.span.end.clamp(self.span.start, line_end)]
+            .chars()
+            .count();
+
+        format!("{}\n{}\n{}{}", self, line, padding, "^".repeat(underlined.max(1)))
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.message, self.span.line, self.span.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{errors::SyntaxError, tokenizer::Span};
+
+    #[test]
+    fn render_points_at_span() {
+        let error = SyntaxError::new(
+            String::from("Unexpected token"),
+            Span {
+                start: 13,
+                end: 14,
+                line: 1,
+                column: 14,
+            },
+        );
+
+        assert_eq!(
+            "Unexpected token at 1:14\nCREATE TABLE (name string);\n             ^",
+            error.render("CREATE TABLE (name string);")
+        );
+    }
+
+    #[test]
+    fn render_multi_line_query() {
+        let query = "CREATE TABLE users\n\t(name string) garbage;\nDELETE TABLE users;";
+        let error = SyntaxError::new(
+            String::from("Unexpected token"),
+            Span {
+                start: 34,
+                end: 41,
+                line: 2,
+                column: 16,
+            },
+        );
+
+        assert_eq!(
+            "Unexpected token at 2:16\n\t(name string) garbage;\n\t              ^^^^^^^",
+            error.render(query)
+        );
+    }
+
+    #[test]
+    fn render_end_of_input() {
+        let error = SyntaxError::new(
+            String::from("Unexpected end of token stream"),
+            Span {
+                start: 12,
+                end: 12,
+                line: 1,
+                column: 13,
+            },
+        );
+
+        assert_eq!(
+            "Unexpected end of token stream at 1:13\nCREATE TABLE\n            ^",
+            error.render("CREATE TABLE")
+        );
+    }
 }
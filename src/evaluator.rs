@@ -0,0 +1,305 @@
+use std::cmp::Ordering;
+
+use crate::{
+    ast::{formatter::Formatter, BinaryOperator, ColumnRef, Expr, UnaryOperator},
+    database::ColumnDescription,
+    errors::DbErr,
+    field_value::FieldValue,
+};
+
+/// Evaluates expressions against the rows of one table, following SQL's rules for NULL
+pub(crate) struct Evaluator<'a> {
+    /// Qualified column references have to name this table. `None` accepts any qualifier.
+    table_name: Option<&'a str>,
+    columns: &'a [ColumnDescription],
+}
+
+impl<'a> Evaluator<'a> {
+    pub(crate) fn new(table_name: Option<&'a str>, columns: &'a [ColumnDescription]) -> Evaluator<'a> {
+        Evaluator { table_name, columns }
+    }
+
+    /// An evaluator for expressions that can't refer to any column, such as defaults
+    pub(crate) fn constant() -> Evaluator<'static> {
+        Evaluator {
+            table_name: None,
+            columns: &[],
+        }
+    }
+
+    /// The position of a referenced column in the table's rows
+    pub(crate) fn column_index(&self, column: &ColumnRef) -> Result<usize, DbErr> {
+        let qualifier_matches = match (&column.table, self.table_name) {
+            (Some(qualifier), Some(table_name)) => qualifier == table_name,
+            _ => true,
+        };
+
+        self.columns
+            .iter()
+            .position(|description| description.column_name == column.column)
+            .filter(|_| qualifier_matches)
+            .ok_or_else(|| DbErr::ColumnNotExists(column.column.clone()))
+    }
+
+    /// Fails if the expression refers to a column the table doesn't have, without evaluating anything
+    pub(crate) fn check_columns(&self, expr: &Expr) -> Result<(), DbErr> {
+        expr.columns()
+            .into_iter()
+            .try_for_each(|column| self.column_index(column).map(|_| ()))
+    }
+
+    /// Whether a row passes a `WHERE` condition. Rows the condition is unknown for don't pass.
+    pub(crate) fn matches(&self, condition: &Expr, row: &[FieldValue]) -> Result<bool, DbErr> {
+        Ok(self.evaluate(condition, row)?.truth()? == Some(true))
+    }
+
+    pub(crate) fn evaluate(&self, expr: &Expr, row: &[FieldValue]) -> Result<FieldValue, DbErr> {
+        match expr {
+            Expr::Literal(literal) => Ok(FieldValue::from(literal)),
+            Expr::Parameter(idx) => Err(DbErr::Generic(format!(
+                "Parameter ${} has no value, it can only be given to a prepared statement",
+                idx + 1
+            ))),
+            Expr::Column(column) => Ok(row[self.column_index(column)?].clone()),
+            Expr::Unary { operator, operand } => {
+                let operand = self.evaluate(operand, row)?;
+                match operator {
+                    UnaryOperator::Not => operand.not(),
+                    UnaryOperator::Plus => match operand {
+                        FieldValue::Null | FieldValue::Integer(_) | FieldValue::Double(_) => Ok(operand),
+                        operand => Err(operand_mismatch("+", &operand)),
+                    },
+                    UnaryOperator::Minus => match operand {
+                        FieldValue::Null => Ok(FieldValue::Null),
+                        FieldValue::Integer(value) => value.checked_neg().map(FieldValue::Integer).ok_or_else(overflow),
+                        FieldValue::Double(value) => Ok(FieldValue::Double(-value)),
+                        operand => Err(operand_mismatch("-", &operand)),
+                    },
+                }
+            }
+            Expr::Binary { left, operator, right } => {
+                let left = self.evaluate(left, row)?;
+                let right = self.evaluate(right, row)?;
+                binary(&left, *operator, &right)
+            }
+            Expr::IsNull { operand, negated } => Ok(FieldValue::Boolean(self.evaluate(operand, row)?.is_null() != *negated)),
+            Expr::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => {
+                let operand = self.evaluate(operand, row)?;
+                let above_low = compare(&operand, BinaryOperator::GreaterOrEqual, &self.evaluate(low, row)?)?;
+                let below_high = compare(&operand, BinaryOperator::LessOrEqual, &self.evaluate(high, row)?)?;
+                let between = above_low.and(&below_high)?;
+
+                if *negated {
+                    between.not()
+                } else {
+                    Ok(between)
+                }
+            }
+            Expr::InList { operand, list, negated } => {
+                let operand = self.evaluate(operand, row)?;
+
+                // TRUE if any item is equal, otherwise unknown if any comparison was
+                let mut found = FieldValue::Boolean(false);
+                for item in list {
+                    found = found.or(&compare(&operand, BinaryOperator::Equals, &self.evaluate(item, row)?)?)?;
+                    if found == FieldValue::Boolean(true) {
+                        break;
+                    }
+                }
+
+                if *negated {
+                    found.not()
+                } else {
+                    Ok(found)
+                }
+            }
+        }
+    }
+}
+
+fn binary(left: &FieldValue, operator: BinaryOperator, right: &FieldValue) -> Result<FieldValue, DbErr> {
+    match operator {
+        BinaryOperator::And => left.and(right),
+        BinaryOperator::Or => left.or(right),
+        BinaryOperator::Equals
+        | BinaryOperator::NotEquals
+        | BinaryOperator::LessThan
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterOrEqual => compare(left, operator, right),
+        BinaryOperator::Concat => concat(left, right),
+        BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => {
+            arithmetic(left, operator, right)
+        }
+    }
+}
+
+fn compare(left: &FieldValue, operator: BinaryOperator, right: &FieldValue) -> Result<FieldValue, DbErr> {
+    Ok(FieldValue::from(left.sql_cmp(right)?.map(|ordering| match operator {
+        BinaryOperator::Equals => ordering == Ordering::Equal,
+        BinaryOperator::NotEquals => ordering != Ordering::Equal,
+        BinaryOperator::LessThan => ordering == Ordering::Less,
+        BinaryOperator::LessOrEqual => ordering != Ordering::Greater,
+        BinaryOperator::GreaterThan => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    })))
+}
+
+/// `||` joins blobs with blobs, and anything else as text
+fn concat(left: &FieldValue, right: &FieldValue) -> Result<FieldValue, DbErr> {
+    match (left, right) {
+        (FieldValue::Null, _) | (_, FieldValue::Null) => Ok(FieldValue::Null),
+        (FieldValue::Blob(left), FieldValue::Blob(right)) => Ok(FieldValue::Blob([left.as_slice(), right].concat())),
+        (FieldValue::Blob(_), _) | (_, FieldValue::Blob(_)) => Err(DbErr::TypeMismatch(format!(
+            "Can't concatenate {} with {}",
+            left.type_name(),
+            right.type_name()
+        ))),
+        _ => Ok(FieldValue::Text(format!("{}{}", left, right))),
+    }
+}
+
+/// Integers stay integers, with overflow being an error, and mixing in a double makes the result a double
+fn arithmetic(left: &FieldValue, operator: BinaryOperator, right: &FieldValue) -> Result<FieldValue, DbErr> {
+    match (left, right) {
+        (FieldValue::Null, _) | (_, FieldValue::Null) => Ok(FieldValue::Null),
+        (FieldValue::Integer(left), FieldValue::Integer(right)) => {
+            let result = match operator {
+                BinaryOperator::Plus => left.checked_add(*right),
+                BinaryOperator::Minus => left.checked_sub(*right),
+                BinaryOperator::Multiply => left.checked_mul(*right),
+                _ if *right == 0 => return Err(DbErr::ArithmeticError(String::from("Division by zero"))),
+                BinaryOperator::Divide => left.checked_div(*right),
+                _ => left.checked_rem(*right),
+            };
+            result.map(FieldValue::Integer).ok_or_else(overflow)
+        }
+        (FieldValue::Integer(_) | FieldValue::Double(_), FieldValue::Integer(_) | FieldValue::Double(_)) => {
+            let (left, right) = (as_double(left), as_double(right));
+            Ok(FieldValue::Double(match operator {
+                BinaryOperator::Plus => left + right,
+                BinaryOperator::Minus => left - right,
+                BinaryOperator::Multiply => left * right,
+                _ if right == 0.0 => return Err(DbErr::ArithmeticError(String::from("Division by zero"))),
+                BinaryOperator::Divide => left / right,
+                _ => left % right,
+            }))
+        }
+        _ => Err(DbErr::TypeMismatch(format!(
+            "Can't apply {} to {} and {}",
+            Formatter::operator_text(operator),
+            left.type_name(),
+            right.type_name()
+        ))),
+    }
+}
+
+fn as_double(value: &FieldValue) -> f64 {
+    match value {
+        FieldValue::Integer(value) => *value as f64,
+        FieldValue::Double(value) => *value,
+        _ => unreachable!("only numbers are converted"),
+    }
+}
+
+fn overflow() -> DbErr {
+    DbErr::ArithmeticError(String::from("Integer out of range"))
+}
+
+fn operand_mismatch(operator: &str, operand: &FieldValue) -> DbErr {
+    DbErr::TypeMismatch(format!("Can't apply {} to {}", operator, operand.type_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::Expr,
+        data_type::DataType,
+        database::ColumnDescription,
+        errors::DbErr,
+        evaluator::Evaluator,
+        field_value::FieldValue,
+        queries::{expression_parser::parse_expression, token_cursor::TokenCursor},
+        tokenizer::Tokenizer,
+    };
+
+    fn parse(expression: &str) -> Expr {
+        let tokens = Tokenizer::new().tokenize_spanned(expression).unwrap();
+        parse_expression(&mut TokenCursor::new(&tokens)).unwrap()
+    }
+
+    fn columns() -> Vec<ColumnDescription> {
+        vec![
+            ColumnDescription::new("a", DataType::Integer),
+            ColumnDescription::new("b", DataType::Text),
+            ColumnDescription::new("c", DataType::Integer),
+        ]
+    }
+
+    fn evaluate(expression: &str) -> Result<FieldValue, DbErr> {
+        let columns = columns();
+        let row = [FieldValue::Integer(7), FieldValue::Text(String::from("x")), FieldValue::Null];
+
+        Evaluator::new(Some("t"), &columns).evaluate(&parse(expression), &row)
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Ok(FieldValue::Integer(15)), evaluate("1 + a * 2"));
+        assert_eq!(Ok(FieldValue::Integer(3)), evaluate("a / 2"));
+        assert_eq!(Ok(FieldValue::Integer(-1)), evaluate("-a % 2"));
+        assert_eq!(Ok(FieldValue::Double(3.5)), evaluate("a / 2.0"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("c + 1"));
+        assert_eq!(Ok(FieldValue::Text(String::from("x7"))), evaluate("b || t.a"));
+        assert_eq!(Err(DbErr::ArithmeticError(String::from("Division by zero"))), evaluate("a / 0"));
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from("Integer out of range"))),
+            evaluate("9223372036854775807 + a")
+        );
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't apply + to TEXT and INTEGER"))),
+            evaluate("b + 1")
+        );
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("a = 7 AND b <> 'y'"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("c = 1"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("c = 1 OR a = 1"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("c = 1 OR a = 7"));
+        assert_eq!(Ok(FieldValue::Boolean(false)), evaluate("c = 1 AND a = 1"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("NOT a > 7"));
+        assert!(evaluate("b < 1").is_err());
+    }
+
+    #[test]
+    fn null_between_and_in() {
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("c IS NULL AND a IS NOT NULL"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("a BETWEEN 1 AND 7"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("a NOT BETWEEN 8 AND 9"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("a BETWEEN c AND 9"));
+        assert_eq!(Ok(FieldValue::Boolean(false)), evaluate("a BETWEEN c AND 6"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("a IN (1, 7.0)"));
+        assert_eq!(Ok(FieldValue::Boolean(false)), evaluate("a IN (1, 2)"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("a IN (1, c)"));
+        assert_eq!(Ok(FieldValue::Boolean(true)), evaluate("a IN (c, 7)"));
+        assert_eq!(Ok(FieldValue::Null), evaluate("a NOT IN (1, c)"));
+    }
+
+    #[test]
+    fn unknown_columns() {
+        assert_eq!(Err(DbErr::ColumnNotExists(String::from("d"))), evaluate("d + 1"));
+        assert_eq!(Err(DbErr::ColumnNotExists(String::from("a"))), evaluate("other.a"));
+        assert_eq!(
+            Err(DbErr::ColumnNotExists(String::from("a"))),
+            Evaluator::constant().check_columns(&parse("1 + a"))
+        );
+        assert_eq!(Ok(()), Evaluator::new(None, &columns()).check_columns(&parse("x.a + c")));
+    }
+}
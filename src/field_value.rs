@@ -0,0 +1,338 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use crate::{ast::Literal, errors::DbErr};
+
+/// A single value, as stored in a row, bound to a parameter or returned by a query.
+///
+/// Values have a total order so they can be sorted and used as keys: NULL sorts first, then booleans, numbers,
+/// text and blobs. Integers and doubles compare by numeric value, so `1` and `1.0` are equal and hash the same,
+/// and NaN equals itself and sorts after every other number. SQL comparisons, which are unknown when either
+/// side is NULL, are done with [`FieldValue::sql_cmp`] instead.
+#[derive(Debug, Clone)]
+pub enum FieldValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Double(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl FieldValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, FieldValue::Null)
+    }
+
+    /// Names the kind of value in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldValue::Null => "NULL",
+            FieldValue::Boolean(_) => "BOOLEAN",
+            FieldValue::Integer(_) => "INTEGER",
+            FieldValue::Double(_) => "DOUBLE",
+            FieldValue::Text(_) => "TEXT",
+            FieldValue::Blob(_) => "BLOB",
+        }
+    }
+
+    /// Compares two values as SQL does. The result is unknown if either side is NULL, and values of kinds that
+    /// can't be compared, such as text and numbers, are an error.
+    pub fn sql_cmp(&self, other: &FieldValue) -> Result<Option<Ordering>, DbErr> {
+        if self.is_null() || other.is_null() {
+            return Ok(None);
+        }
+
+        if self.rank() != other.rank() {
+            return Err(DbErr::TypeMismatch(format!(
+                "Can't compare {} with {}",
+                self.type_name(),
+                other.type_name()
+            )));
+        }
+
+        Ok(Some(self.cmp(other)))
+    }
+
+    /// The truth value of a boolean, where NULL is unknown
+    pub fn truth(&self) -> Result<Option<bool>, DbErr> {
+        match self {
+            FieldValue::Null => Ok(None),
+            FieldValue::Boolean(value) => Ok(Some(*value)),
+            value => Err(DbErr::TypeMismatch(format!("Expected a BOOLEAN, got {}", value.type_name()))),
+        }
+    }
+
+    /// `AND` with SQL's three valued logic, where FALSE wins over unknown
+    pub fn and(&self, other: &FieldValue) -> Result<FieldValue, DbErr> {
+        Ok(FieldValue::from(match (self.truth()?, other.truth()?) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        }))
+    }
+
+    /// `OR` with SQL's three valued logic, where TRUE wins over unknown
+    pub fn or(&self, other: &FieldValue) -> Result<FieldValue, DbErr> {
+        Ok(FieldValue::from(match (self.truth()?, other.truth()?) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        }))
+    }
+
+    pub fn not(&self) -> Result<FieldValue, DbErr> {
+        Ok(FieldValue::from(self.truth()?.map(|value| !value)))
+    }
+
+    /// Orders the kinds of value relative to each other. Integers and doubles share a rank.
+    fn rank(&self) -> u8 {
+        match self {
+            FieldValue::Null => 0,
+            FieldValue::Boolean(_) => 1,
+            FieldValue::Integer(_) | FieldValue::Double(_) => 2,
+            FieldValue::Text(_) => 3,
+            FieldValue::Blob(_) => 4,
+        }
+    }
+}
+
+/// Compares an integer with a double exactly, without the rounding that converting either one would cause
+fn cmp_integer_double(integer: i64, double: f64) -> Ordering {
+    // 2^63, the first double above every i64
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if double.is_nan() || double >= LIMIT {
+        Ordering::Less
+    } else if double < -LIMIT {
+        Ordering::Greater
+    } else {
+        // The double is now within range, so truncating it only loses the fraction
+        let truncated = double.trunc();
+        integer
+            .cmp(&(truncated as i64))
+            .then_with(|| 0.0_f64.partial_cmp(&(double - truncated)).unwrap())
+    }
+}
+
+fn cmp_doubles(left: f64, right: f64) -> Ordering {
+    match (left.is_nan(), right.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // Neither is NaN, and -0.0 equals 0.0
+        (false, false) => left.partial_cmp(&right).unwrap(),
+    }
+}
+
+impl Ord for FieldValue {
+    fn cmp(&self, other: &FieldValue) -> Ordering {
+        match (self, other) {
+            (FieldValue::Boolean(left), FieldValue::Boolean(right)) => left.cmp(right),
+            (FieldValue::Integer(left), FieldValue::Integer(right)) => left.cmp(right),
+            (FieldValue::Integer(left), FieldValue::Double(right)) => cmp_integer_double(*left, *right),
+            (FieldValue::Double(left), FieldValue::Integer(right)) => cmp_integer_double(*right, *left).reverse(),
+            (FieldValue::Double(left), FieldValue::Double(right)) => cmp_doubles(*left, *right),
+            (FieldValue::Text(left), FieldValue::Text(right)) => left.cmp(right),
+            (FieldValue::Blob(left), FieldValue::Blob(right)) => left.cmp(right),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for FieldValue {
+    fn partial_cmp(&self, other: &FieldValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FieldValue {
+    fn eq(&self, other: &FieldValue) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FieldValue {}
+
+impl Hash for FieldValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+
+        match self {
+            FieldValue::Null => {}
+            FieldValue::Boolean(value) => value.hash(state),
+            FieldValue::Integer(value) => value.hash(state),
+            // Doubles that equal an integer have to hash like that integer
+            FieldValue::Double(value) if value.fract() == 0.0 && cmp_integer_double(*value as i64, *value).is_eq() => {
+                (*value as i64).hash(state)
+            }
+            FieldValue::Double(value) if value.is_nan() => f64::NAN.to_bits().hash(state),
+            FieldValue::Double(value) => value.to_bits().hash(state),
+            FieldValue::Text(value) => value.hash(state),
+            FieldValue::Blob(value) => value.hash(state),
+        }
+    }
+}
+
+impl From<Option<bool>> for FieldValue {
+    fn from(value: Option<bool>) -> FieldValue {
+        value.map_or(FieldValue::Null, FieldValue::Boolean)
+    }
+}
+
+impl From<&Literal> for FieldValue {
+    fn from(literal: &Literal) -> FieldValue {
+        match literal {
+            Literal::Null => FieldValue::Null,
+            Literal::Boolean(value) => FieldValue::Boolean(*value),
+            Literal::Integer(value) => FieldValue::Integer(*value),
+            Literal::Float(value) => FieldValue::Double(*value),
+            Literal::String(value) => FieldValue::Text(value.clone()),
+            Literal::Blob(value) => FieldValue::Blob(value.clone()),
+        }
+    }
+}
+
+impl From<&FieldValue> for Literal {
+    fn from(value: &FieldValue) -> Literal {
+        match value {
+            FieldValue::Null => Literal::Null,
+            FieldValue::Boolean(value) => Literal::Boolean(*value),
+            FieldValue::Integer(value) => Literal::Integer(*value),
+            FieldValue::Double(value) => Literal::Float(*value),
+            FieldValue::Text(value) => Literal::String(value.clone()),
+            FieldValue::Blob(value) => Literal::Blob(value.clone()),
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Null => write!(f, "NULL"),
+            FieldValue::Boolean(true) => write!(f, "TRUE"),
+            FieldValue::Boolean(false) => write!(f, "FALSE"),
+            FieldValue::Integer(value) => write!(f, "{}", value),
+            FieldValue::Double(value) => write!(f, "{:?}", value),
+            FieldValue::Text(value) => write!(f, "{}", value),
+            FieldValue::Blob(value) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "'")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cmp::Ordering,
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    use crate::{errors::DbErr, field_value::FieldValue};
+
+    fn hash(value: &FieldValue) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn order_across_kinds() {
+        let mut values = vec![
+            FieldValue::Blob(vec![1]),
+            FieldValue::Text(String::from("a")),
+            FieldValue::Double(f64::NAN),
+            FieldValue::Integer(2),
+            FieldValue::Double(1.5),
+            FieldValue::Boolean(true),
+            FieldValue::Null,
+            FieldValue::Integer(i64::MIN),
+            FieldValue::Double(f64::NEG_INFINITY),
+        ];
+        values.sort();
+
+        assert_eq!(
+            vec![
+                FieldValue::Null,
+                FieldValue::Boolean(true),
+                FieldValue::Double(f64::NEG_INFINITY),
+                FieldValue::Integer(i64::MIN),
+                FieldValue::Double(1.5),
+                FieldValue::Integer(2),
+                FieldValue::Double(f64::NAN),
+                FieldValue::Text(String::from("a")),
+                FieldValue::Blob(vec![1]),
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn integers_and_doubles() {
+        assert_eq!(FieldValue::Integer(1), FieldValue::Double(1.0));
+        assert_eq!(FieldValue::Double(-0.0), FieldValue::Integer(0));
+        assert_eq!(FieldValue::Double(f64::NAN), FieldValue::Double(f64::NAN));
+        assert!(FieldValue::Integer(1) < FieldValue::Double(1.000001));
+        assert!(FieldValue::Double(0.5) > FieldValue::Integer(0));
+        assert!(FieldValue::Double(-0.5) < FieldValue::Integer(0));
+
+        // i64::MAX can't be represented exactly as a double, which rounds up to 2^63
+        assert!(FieldValue::Integer(i64::MAX) < FieldValue::Double(i64::MAX as f64));
+        assert_eq!(FieldValue::Integer(i64::MIN), FieldValue::Double(i64::MIN as f64));
+    }
+
+    #[test]
+    fn equal_values_hash_equally() {
+        assert_eq!(hash(&FieldValue::Integer(3)), hash(&FieldValue::Double(3.0)));
+        assert_eq!(hash(&FieldValue::Integer(0)), hash(&FieldValue::Double(-0.0)));
+        assert_eq!(hash(&FieldValue::Double(f64::NAN)), hash(&FieldValue::Double(-f64::NAN)));
+        assert_ne!(hash(&FieldValue::Integer(1)), hash(&FieldValue::Boolean(true)));
+    }
+
+    #[test]
+    fn sql_comparisons() {
+        assert_eq!(Ok(None), FieldValue::Null.sql_cmp(&FieldValue::Null));
+        assert_eq!(Ok(None), FieldValue::Integer(1).sql_cmp(&FieldValue::Null));
+        assert_eq!(Ok(Some(Ordering::Less)), FieldValue::Integer(1).sql_cmp(&FieldValue::Double(2.5)));
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't compare TEXT with INTEGER"))),
+            FieldValue::Text(String::from("1")).sql_cmp(&FieldValue::Integer(1))
+        );
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        let values = [FieldValue::Boolean(true), FieldValue::Boolean(false), FieldValue::Null];
+        // Rows follow the left operand and columns the right one, in the order TRUE, FALSE, NULL
+        let and = ["TRUE FALSE NULL", "FALSE FALSE FALSE", "NULL FALSE NULL"];
+        let or = ["TRUE TRUE TRUE", "TRUE FALSE NULL", "TRUE NULL NULL"];
+
+        for (left, (and, or)) in values.iter().zip(and.iter().zip(or)) {
+            let and_row: Vec<String> = values.iter().map(|right| left.and(right).unwrap().to_string()).collect();
+            let or_row: Vec<String> = values.iter().map(|right| left.or(right).unwrap().to_string()).collect();
+
+            assert_eq!(*and, and_row.join(" "));
+            assert_eq!(or, or_row.join(" "));
+        }
+
+        assert_eq!(Ok(FieldValue::Null), FieldValue::Null.not());
+        assert_eq!(Ok(FieldValue::Boolean(false)), FieldValue::Boolean(true).not());
+        assert!(FieldValue::Integer(1).and(&FieldValue::Boolean(true)).is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("NULL", FieldValue::Null.to_string());
+        assert_eq!("2.0", FieldValue::Double(2.0).to_string());
+        assert_eq!("X'0AFF'", FieldValue::Blob(vec![10, 255]).to_string());
+    }
+}
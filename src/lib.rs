@@ -0,0 +1,11 @@
+pub mod ast;
+mod constraints;
+pub mod data_type;
+pub mod database;
+pub mod errors;
+mod evaluator;
+pub mod field_value;
+pub mod queries;
+pub mod row_store;
+pub mod shell;
+pub mod tokenizer;
@@ -1,11 +1,23 @@
-use crate::{database::Database, queries::query_parser::execute_query};
+use std::io::{self, BufRead, Write};
 
-mod database;
-mod errors;
-mod queries;
-mod tokenizer;
+use testing_db::{database::Database, shell::Shell};
 
 fn main() {
-    let mut database = Database::new();
-    let _ = execute_query(&mut database, "My query");
+    let mut shell = Shell::new(Database::new());
+    let stdin = io::stdin();
+
+    loop {
+        print!("{}", if shell.is_continuing() { "...> " } else { "db> " });
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        if let Some(output) = shell.handle_line(&line) {
+            println!("{}", output);
+        }
+    }
 }
@@ -1,88 +1,263 @@
-use std::collections::HashMap;
+use crate::{
+    ast::{ColumnConstraint, ColumnConstraintKind, ColumnDef, CreateTable, Statement, TableConstraint, TableConstraintKind, TableRef},
+    constraints::{self, Constraint, ConstraintKind},
+    data_type::DataType,
+    database::{ColumnDescription, Database},
+    errors::DbErr,
+    evaluator::Evaluator,
+    tokenizer::Token,
+};
 
-use crate::{database::ColumnDescription, errors::DbErr, tokenizer::Token};
-
-use super::{Query, QuerySuccess};
+use super::{
+    parse_steps::{Capture, Captures},
+    Query, QuerySuccess,
+};
 
 pub(crate) struct CreateTableQuery {
-    data: HashMap<String, Token>,
+    statement: CreateTable,
 }
 
 impl Query for CreateTableQuery {
-    fn new(data: HashMap<String, Token>) -> CreateTableQuery {
-        CreateTableQuery { data }
+    fn new(mut captures: Captures) -> Result<CreateTableQuery, DbErr> {
+        let mut columns: Vec<ColumnDef> = Vec::new();
+        let mut constraints: Vec<TableConstraint> = Vec::new();
+
+        for mut element in captures.take_repeated("Elements")? {
+            match element.take_choice("Element")? {
+                (0, column) => columns.push(column_definition(column)?),
+                (_, constraint) => constraints.push(table_constraint(constraint)?),
+            }
+        }
+
+        Ok(CreateTableQuery {
+            statement: CreateTable {
+                table: TableRef::new(&captures.take_identifier("Name")?),
+                columns,
+                constraints,
+                if_not_exists: captures.take_optional("IfNotExists")?.is_some(),
+            },
+        })
     }
-    fn execute(&self, database: &mut crate::database::Database) -> Result<QuerySuccess, DbErr> {
-        let table_name = self.get_table_name()?;
-        let table_description = self.get_table_description()?;
 
-        match database.create_table(table_name, table_description) {
-            Ok(_table) => Ok(QuerySuccess::new()),
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        if self.statement.if_not_exists && database.get_table(&self.statement.table.name).is_some() {
+            return Ok(QuerySuccess::Done);
+        }
+
+        let (columns, constraints) = self.table_definition()?;
+        match database.create_table_with_constraints(&self.statement.table.name, columns, constraints) {
+            Ok(_table) => Ok(QuerySuccess::Done),
             Err(err) => Err(err),
         }
     }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::CreateTable(self.statement.clone()))
+    }
 }
 
 impl CreateTableQuery {
-    fn get_table_name(&self) -> Result<&String, DbErr> {
-        if let Some(Token::Identifier(table_name)) = self.data.get("Name") {
-            Ok(table_name)
-        } else {
-            Err(DbErr::Generic(String::from("Bad name param")))
-        }
-    }
+    /// The columns and constraints to store. Constraints written on a column are stored like table constraints,
+    /// and unnamed ones are named the way PostgreSQL names them, such as `users_pkey` or `users_email_key`.
+    fn table_definition(&self) -> Result<(Vec<ColumnDescription>, Vec<Constraint>), DbErr> {
+        let table_name = &self.statement.table.name;
+        let mut columns: Vec<ColumnDescription> = Vec::new();
+        let mut table_constraints: Vec<TableConstraint> = Vec::new();
 
-    fn get_table_description(&self) -> Result<Vec<ColumnDescription>, DbErr> {
-        if let Some(Token::List(tokens)) = self.data.get("TableDescription") {
-            let mut i = 0;
-            let mut column_descriptions: Vec<ColumnDescription> = Vec::new();
+        for column in &self.statement.columns {
+            let mut description = ColumnDescription::new(&column.name, column.data_type);
 
-            while i < tokens.len() {
-                match (&tokens[i], &tokens[i + 1]) {
-                    (Token::Identifier(first), Token::Identifier(second)) => {
-                        column_descriptions.push(ColumnDescription {
-                            column_name: first.clone(),
-                            column_type: second.clone(),
-                        });
+            for constraint in &column.constraints {
+                let kind = match &constraint.kind {
+                    ColumnConstraintKind::NotNull => {
+                        description.not_null = true;
+                        continue;
+                    }
+                    ColumnConstraintKind::Default(expr) => {
+                        if !expr.columns().is_empty() {
+                            return Err(DbErr::InvalidConstraint(format!(
+                                "The default of column {} can't refer to columns",
+                                column.name
+                            )));
+                        }
+                        Evaluator::constant().evaluate(expr, &[])?;
+                        description.default = Some(expr.clone());
+                        continue;
+                    }
+                    ColumnConstraintKind::Unique => TableConstraintKind::Unique(vec![column.name.clone()]),
+                    ColumnConstraintKind::PrimaryKey => TableConstraintKind::PrimaryKey(vec![column.name.clone()]),
+                    ColumnConstraintKind::Check(expr) => TableConstraintKind::Check(expr.clone()),
+                };
 
-                        i += 2; // Move to the next potential series
+                table_constraints.push(TableConstraint {
+                    name: constraint.name.clone(),
+                    kind,
+                });
+            }
+
+            columns.push(description);
+        }
+        table_constraints.extend(self.statement.constraints.iter().cloned());
+
+        let evaluator = Evaluator::new(Some(table_name), &columns);
+        let mut constraints: Vec<Constraint> = Vec::new();
+        for constraint in table_constraints {
+            let kind = match constraint.kind {
+                TableConstraintKind::PrimaryKey(names) => {
+                    if constraints
+                        .iter()
+                        .any(|existing| matches!(existing.kind, ConstraintKind::PrimaryKey(_)))
+                    {
+                        return Err(DbErr::InvalidConstraint(format!(
+                            "Table {} has more than one primary key",
+                            table_name
+                        )));
                     }
-                    _ => return Err(DbErr::Generic(String::from("Bad table description provided"))),
+                    ConstraintKind::PrimaryKey(constraints::index_columns(&columns, &names)?)
+                }
+                TableConstraintKind::Unique(names) => ConstraintKind::Unique(constraints::index_columns(&columns, &names)?),
+                TableConstraintKind::Check(expr) => {
+                    evaluator.check_columns(&expr)?;
+                    ConstraintKind::Check(expr)
                 }
+            };
 
-                match tokens.get(i) {
-                    None => return Ok(column_descriptions),
-                    Some(Token::Comma) => i += 1,
-                    _ => return Err(DbErr::Generic(String::from("Unexpected item where , or nothing was expected"))),
+            let name = match constraint.name {
+                Some(name) if constraints.iter().any(|existing| existing.name == name) => {
+                    return Err(DbErr::InvalidConstraint(format!("Constraint {} is defined more than once", name)));
                 }
+                Some(name) => name,
+                None => unique_name(&constraints, generated_name(table_name, &columns, &kind)),
+            };
+            constraints.push(Constraint { name, kind });
+        }
+
+        Ok((columns, constraints))
+    }
+}
+
+fn column_definition(mut column: Captures) -> Result<ColumnDef, DbErr> {
+    let type_name = column.take_identifier("Type")?;
+    let mut parameters: Vec<i64> = Vec::new();
+
+    if let Some(mut captured) = column.take_optional("Parameters")? {
+        for mut parameter in captured.take_repeated("Values")? {
+            match parameter.take("Value")? {
+                Capture::Token(Token::Integer(value)) => parameters.push(value),
+                capture => return Err(DbErr::Generic(format!("Bad type parameter {:?}", capture))),
             }
-        } else {
-            return Err(DbErr::Generic(String::from("Bad name param")));
         }
+    }
+
+    let constraints = column
+        .take_repeated("Constraints")?
+        .into_iter()
+        .map(|mut constraint| {
+            let kind = match constraint.take_choice("Kind")? {
+                (0, _) => ColumnConstraintKind::NotNull,
+                (1, mut captured) => ColumnConstraintKind::Default(captured.take_expression("Default")?),
+                (2, _) => ColumnConstraintKind::Unique,
+                (3, _) => ColumnConstraintKind::PrimaryKey,
+                (_, mut captured) => ColumnConstraintKind::Check(captured.take_expression("Check")?),
+            };
+
+            Ok(ColumnConstraint {
+                name: constraint_name(&mut constraint)?,
+                kind,
+            })
+        })
+        .collect::<Result<Vec<ColumnConstraint>, DbErr>>()?;
+
+    Ok(ColumnDef {
+        name: column.take_identifier("Name")?,
+        data_type: DataType::from_name(&type_name, &parameters)?,
+        constraints,
+    })
+}
+
+fn table_constraint(mut constraint: Captures) -> Result<TableConstraint, DbErr> {
+    let column_names = |mut captured: Captures| {
+        captured
+            .take_repeated("Columns")?
+            .into_iter()
+            .map(|mut column| column.take_identifier("Name"))
+            .collect::<Result<Vec<String>, DbErr>>()
+    };
+
+    let kind = match constraint.take_choice("Kind")? {
+        (0, captured) => TableConstraintKind::PrimaryKey(column_names(captured)?),
+        (1, captured) => TableConstraintKind::Unique(column_names(captured)?),
+        (_, mut captured) => TableConstraintKind::Check(captured.take_expression("Check")?),
+    };
+
+    Ok(TableConstraint {
+        name: constraint_name(&mut constraint)?,
+        kind,
+    })
+}
+
+/// The name given with `CONSTRAINT name`, if any
+fn constraint_name(constraint: &mut Captures) -> Result<Option<String>, DbErr> {
+    constraint
+        .take_optional("ConstraintName")?
+        .map(|mut captured| captured.take_identifier("Name"))
+        .transpose()
+}
+
+fn generated_name(table_name: &str, columns: &[ColumnDescription], kind: &ConstraintKind) -> String {
+    let column_name = |idx: &usize| columns[*idx].column_name.as_str();
+
+    match kind {
+        ConstraintKind::PrimaryKey(_) => format!("{}_pkey", table_name),
+        ConstraintKind::Unique(index) => {
+            let names: Vec<&str> = index.columns.iter().map(column_name).collect();
+            format!("{}_{}_key", table_name, names.join("_"))
+        }
+        ConstraintKind::Check(expr) => match expr.columns().first() {
+            Some(column) => format!("{}_{}_check", table_name, column.column),
+            None => format!("{}_check", table_name),
+        },
+    }
+}
 
-        Err(DbErr::Generic(String::from("Unexpected parsing error")))
+/// Adds a number to a generated name that is already taken, as in `users_check1`
+fn unique_name(constraints: &[Constraint], name: String) -> String {
+    let taken = |candidate: &str| constraints.iter().any(|constraint| constraint.name == candidate);
+    if !taken(&name) {
+        return name;
     }
+
+    (1..)
+        .map(|suffix| format!("{}{}", name, suffix))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{database::Database, errors::DbErr, queries::query_parser};
+    use crate::{
+        constraints::ConstraintKind,
+        data_type::DataType,
+        database::Database,
+        errors::DbErr,
+        queries::query_parser::{self, error_message},
+    };
 
     #[test]
     fn create_table_with_columns() {
         let mut database = Database::new();
 
-        assert!(query_parser::execute_query(&mut database, "CREATE TABLE users (name string, age i32);").is_ok());
+        assert!(query_parser::execute_query(&mut database, "CREATE TABLE users (name string, age int);").is_ok());
 
         let table = database.get_table("users").unwrap();
-        let col1 = table.columns.get(0).unwrap();
+        let col1 = table.columns.first().unwrap();
         let col2 = table.columns.get(1).unwrap();
 
         assert_eq!("name", col1.column_name);
-        assert_eq!("string", col1.column_type);
+        assert_eq!(DataType::Text, col1.column_type);
 
         assert_eq!("age", col2.column_name);
-        assert_eq!("i32", col2.column_type);
+        assert_eq!(DataType::Integer, col2.column_type);
     }
 
     #[test]
@@ -94,5 +269,238 @@ mod tests {
             query_parser::execute_query(&mut database, "CREATE TABLE users (name string);").unwrap_err(),
         );
     }
-    
+
+    #[test]
+    fn create_table_with_quoted_identifiers() {
+        let mut database = Database::new();
+
+        assert!(query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE \"Order Items\" (\"Order Date\" string, `table` INTEGER, \"CamelCase\" INTEGER);"
+        )
+        .is_ok());
+
+        let table = database.get_table("Order Items").unwrap();
+
+        assert_eq!("Order Date", table.columns[0].column_name);
+        assert_eq!("table", table.columns[1].column_name);
+        assert_eq!("CamelCase", table.columns[2].column_name);
+    }
+
+    #[test]
+    fn create_table_if_not_exists() {
+        let mut database = Database::new();
+        let query = "CREATE TABLE IF NOT EXISTS users (name string);";
+
+        assert!(query_parser::execute_query(&mut database, query).is_ok());
+        assert!(query_parser::execute_query(&mut database, query).is_ok());
+        assert_eq!(1, database.get_table("users").unwrap().columns.len());
+    }
+
+    #[test]
+    fn type_parameters() {
+        let mut database = Database::new();
+
+        assert!(query_parser::execute_query(&mut database, "CREATE TABLE t (tags VARCHAR(20), id INTEGER);").is_ok());
+
+        let columns = &database.get_table("t").unwrap().columns;
+        assert_eq!(DataType::Varchar(20), columns[0].column_type);
+        assert_eq!("id", columns[1].column_name);
+
+        assert_eq!("expected integer after `(` at 1:27", error_message("CREATE TABLE t (a VARCHAR());"));
+        assert_eq!(
+            "expected one of `,` or `)` after integer at 1:29",
+            error_message("CREATE TABLE t (a VARCHAR(2 3));")
+        );
+    }
+
+    #[test]
+    fn invalid_data_types() {
+        let mut database = Database::new();
+
+        assert_eq!(
+            DbErr::InvalidDataType(String::from(
+                "Unknown data type banana, expected one of SMALLINT, INTEGER, BIGINT, REAL, DOUBLE, BOOLEAN, TEXT, VARCHAR, BLOB"
+            )),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a banana);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::InvalidDataType(String::from(
+                "Unknown data type DECIMAL, expected one of SMALLINT, INTEGER, BIGINT, REAL, DOUBLE, BOOLEAN, TEXT, VARCHAR, BLOB"
+            )),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (price DECIMAL(10, 2));").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::InvalidDataType(String::from("Unknown data type BOOLEN, did you mean BOOLEAN?")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int, b BOOLEN);").unwrap_err()
+        );
+        assert!(database.get_table("t").is_none());
+    }
+
+    #[test]
+    fn malformed_column_list() {
+        assert_eq!(
+            "expected one of identifier, `CONSTRAINT`, `PRIMARY`, `UNIQUE` or `CHECK` after `(` at 1:17",
+            error_message("CREATE TABLE t ();")
+        );
+        assert_eq!(
+            "expected identifier after column name at 1:18",
+            error_message("CREATE TABLE t (a);")
+        );
+        assert_eq!(
+            "expected identifier after column name at 1:25",
+            error_message("CREATE TABLE t (a int, b, c int);")
+        );
+        assert_eq!(
+            "expected one of identifier, `CONSTRAINT`, `PRIMARY`, `UNIQUE` or `CHECK` after `,` at 1:24",
+            error_message("CREATE TABLE t (a int, );")
+        );
+        assert_eq!("unmatched `(` at 1:16", error_message("CREATE TABLE t (a int "));
+        assert_eq!("expected `NOT` after `IF` at 1:17", error_message("CREATE TABLE IF users (a int);"));
+        assert_eq!(
+            "expected one of `IF` or identifier after `TABLE` at 1:14",
+            error_message("CREATE TABLE (a int);")
+        );
+    }
+
+    #[test]
+    fn column_and_table_constraints() {
+        let mut database = Database::new();
+
+        assert!(query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE users (
+                id INTEGER PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE,
+                age INTEGER DEFAULT 18 CONSTRAINT adult CHECK (age >= 18),
+                team TEXT,
+                UNIQUE (team, email),
+                CHECK (team <> ''),
+                CHECK (1 = 1)
+            );"
+        )
+        .is_ok());
+
+        let table = database.get_table("users").unwrap();
+        assert!(table.columns[1].not_null);
+        assert!(!table.columns[2].not_null);
+        assert!(table.columns[2].default.is_some());
+
+        let names: Vec<&str> = table.constraints.iter().map(|constraint| constraint.name.as_str()).collect();
+        assert_eq!(
+            vec![
+                "users_pkey",
+                "users_email_key",
+                "adult",
+                "users_team_email_key",
+                "users_team_check",
+                "users_check"
+            ],
+            names
+        );
+        match &table.constraints[3].kind {
+            ConstraintKind::Unique(index) => assert_eq!(vec![3, 1], index.columns),
+            _ => panic!("Expected a unique constraint"),
+        }
+    }
+
+    #[test]
+    fn constraint_words_name_columns() {
+        let mut database = Database::new();
+
+        assert!(query_parser::execute_query(&mut database, "CREATE TABLE kv (key TEXT PRIMARY KEY, value TEXT);").is_ok());
+        assert!(query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE t (
+                unique INTEGER UNIQUE,
+                check INTEGER CHECK (check > 0),
+                default INTEGER DEFAULT 1 NOT NULL,
+                UNIQUE (check, default),
+                CHECK (unique <> default)
+            );"
+        )
+        .is_ok());
+
+        let table = database.get_table("t").unwrap();
+        let columns: Vec<&str> = table.columns.iter().map(|column| column.column_name.as_str()).collect();
+        assert_eq!(vec!["unique", "check", "default"], columns);
+        assert_eq!(4, table.constraints.len());
+        assert_eq!("kv_pkey", database.get_table("kv").unwrap().constraints[0].name.as_str());
+    }
+
+    #[test]
+    fn generated_names_stay_unique() {
+        let mut database = Database::new();
+
+        assert!(query_parser::execute_query(&mut database, "CREATE TABLE t (a int CHECK (a > 0) CHECK (a < 10));").is_ok());
+
+        let names: Vec<&str> = database
+            .get_table("t")
+            .unwrap()
+            .constraints
+            .iter()
+            .map(|constraint| constraint.name.as_str())
+            .collect();
+        assert_eq!(vec!["t_a_check", "t_a_check1"], names);
+    }
+
+    #[test]
+    fn invalid_constraints() {
+        let mut database = Database::new();
+
+        assert_eq!(
+            DbErr::InvalidConstraint(String::from("Table t has more than one primary key")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int PRIMARY KEY, b int, PRIMARY KEY (a, b));").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("c")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int, UNIQUE (a, c));").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("b")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int CHECK (b > 0));").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::InvalidConstraint(String::from("Constraint positive is defined more than once")),
+            query_parser::execute_query(
+                &mut database,
+                "CREATE TABLE t (a int CONSTRAINT positive CHECK (a > 0), CONSTRAINT positive UNIQUE (a));"
+            )
+            .unwrap_err()
+        );
+        assert_eq!(
+            DbErr::InvalidConstraint(String::from("The default of column b can't refer to columns")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int, b int DEFAULT a + 1);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ArithmeticError(String::from("Division by zero")),
+            query_parser::execute_query(&mut database, "CREATE TABLE t (a int DEFAULT 1 / 0);").unwrap_err()
+        );
+        assert!(database.get_table("t").is_none());
+    }
+
+    #[test]
+    fn malformed_constraints() {
+        assert_eq!(
+            "expected one of `(`, `CONSTRAINT`, `NOT`, `DEFAULT`, `UNIQUE`, `PRIMARY`, `CHECK`, `,` or `)` after table element at 1:23",
+            error_message("CREATE TABLE t (a int NULL);")
+        );
+        assert_eq!(
+            "expected one of `CONSTRAINT`, `NOT`, `DEFAULT`, `UNIQUE`, `PRIMARY`, `CHECK`, `,` or `)` after table element at 1:32",
+            error_message("CREATE TABLE t (a int NOT NULL b);")
+        );
+        assert_eq!("expected `NULL` after `NOT` at 1:26", error_message("CREATE TABLE t (a int NOT);"));
+        assert_eq!(
+            "expected `KEY` after `PRIMARY` at 1:30",
+            error_message("CREATE TABLE t (a int PRIMARY);")
+        );
+        assert_eq!(
+            "expected `(` after `CHECK` at 1:29",
+            error_message("CREATE TABLE t (a int CHECK a > 0);")
+        );
+        assert_eq!(
+            "expected one of `PRIMARY`, `UNIQUE` or `CHECK` after constraint name at 1:30",
+            error_message("CREATE TABLE t (CONSTRAINT c (a));")
+        );
+    }
 }
@@ -0,0 +1,160 @@
+use crate::{
+    ast::{Delete, Statement, TableRef},
+    database::Database,
+    errors::DbErr,
+    evaluator::Evaluator,
+    field_value::FieldValue,
+    row_store::RowId,
+};
+
+use super::{parse_steps::Captures, Query, QuerySuccess};
+
+pub(crate) struct DeleteQuery {
+    statement: Delete,
+}
+
+impl Query for DeleteQuery {
+    fn new(mut captures: Captures) -> Result<DeleteQuery, DbErr> {
+        Ok(DeleteQuery {
+            statement: Delete {
+                table: TableRef::new(&captures.take_identifier("Name")?),
+                selection: captures
+                    .take_optional("Where")?
+                    .map(|mut selection| selection.take_expression("Condition"))
+                    .transpose()?,
+            },
+        })
+    }
+
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        let table_name = &self.statement.table.name;
+        let table = database.get_table_mut(table_name).ok_or(DbErr::TableNotExists)?;
+
+        // Every row is matched before any is deleted, so a condition that fails part way deletes nothing
+        let evaluator = Evaluator::new(Some(table_name), &table.columns);
+        let mut deleted: Vec<RowId> = Vec::new();
+        if let Some(condition) = &self.statement.selection {
+            evaluator.check_columns(condition)?;
+        }
+        for (row_id, row) in table.rows.iter() {
+            let matches = match &self.statement.selection {
+                Some(condition) => evaluator.matches(condition, &row.fields)?,
+                None => true,
+            };
+            if matches {
+                deleted.push(row_id);
+            }
+        }
+
+        for row_id in &deleted {
+            table.delete_row(*row_id);
+        }
+
+        Ok(QuerySuccess::RowsAffected(deleted.len()))
+    }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::Delete(self.statement.clone()))
+    }
+
+    fn bind(&self, _database: &Database, arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        Ok(Box::new(DeleteQuery {
+            statement: Delete {
+                table: self.statement.table.clone(),
+                selection: self.statement.selection.as_ref().map(|condition| condition.bind(arguments)),
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::{query_parser, QuerySuccess},
+    };
+
+    fn database() -> Database {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a INTEGER);
+             INSERT INTO t VALUES (1, 10), (2, NULL), (3, 30), (4, 40);",
+        )
+        .unwrap();
+        database
+    }
+
+    fn ids(database: &Database) -> Vec<FieldValue> {
+        database
+            .get_table("t")
+            .unwrap()
+            .rows
+            .entries()
+            .map(|row| row.fields[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn delete_matching_rows() {
+        let mut database = database();
+
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(2)),
+            query_parser::execute_query(&mut database, "DELETE FROM t WHERE a >= 30;")
+        );
+        assert_eq!(vec![FieldValue::Integer(1), FieldValue::Integer(2)], ids(&database));
+
+        // Rows the condition is unknown for are kept
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(0)),
+            query_parser::execute_query(&mut database, "DELETE FROM t WHERE a <> 10 AND id = 2;")
+        );
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(2)),
+            query_parser::execute_query(&mut database, "DELETE FROM t;")
+        );
+        assert!(ids(&database).is_empty());
+    }
+
+    #[test]
+    fn row_ids_survive_deletes() {
+        let mut database = database();
+        let table = database.get_table("t").unwrap();
+        let last = table.rows.iter().last().unwrap().0;
+
+        query_parser::execute_query(&mut database, "DELETE FROM t WHERE id < 3;").unwrap();
+        let table = database.get_table("t").unwrap();
+        assert_eq!(FieldValue::Integer(4), table.rows.get(last).unwrap().fields[0]);
+
+        // The freed keys and slots can be used again
+        query_parser::execute_query(&mut database, "INSERT INTO t VALUES (1, 0), (2, 0), (5, 0);").unwrap();
+        assert_eq!(5, database.get_table("t").unwrap().rows.len());
+        assert_eq!(
+            FieldValue::Integer(4),
+            database.get_table("t").unwrap().rows.get(last).unwrap().fields[0]
+        );
+        assert!(query_parser::execute_query(&mut database, "UPDATE t SET id = 3 WHERE id = 5;").is_err());
+    }
+
+    #[test]
+    fn failed_deletes_change_nothing() {
+        let mut database = database();
+
+        assert_eq!(
+            DbErr::ArithmeticError(String::from("Division by zero")),
+            query_parser::execute_query(&mut database, "DELETE FROM t WHERE 10 / (id - 3) > 0;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("b")),
+            query_parser::execute_query(&mut database, "DELETE FROM t WHERE b = 1;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TableNotExists,
+            query_parser::execute_query(&mut database, "DELETE FROM missing;").unwrap_err()
+        );
+        assert_eq!(4, ids(&database).len());
+    }
+}
@@ -1,27 +1,38 @@
-use std::collections::HashMap;
-
-use super::{Query, QuerySuccess};
-use crate::{errors::DbErr, tokenizer::Token};
+use super::{parse_steps::Captures, Query, QuerySuccess};
+use crate::{
+    ast::{DeleteTable, Statement, TableRef},
+    database::Database,
+    errors::DbErr,
+};
 
 pub(crate) struct DeleteTableQuery {
-    data: HashMap<String, Token>,
+    statement: DeleteTable,
 }
 
 impl Query for DeleteTableQuery {
-    fn new(data: HashMap<String, Token>) -> DeleteTableQuery {
-        DeleteTableQuery { data }
+    fn new(mut captures: Captures) -> Result<DeleteTableQuery, DbErr> {
+        Ok(DeleteTableQuery {
+            statement: DeleteTable {
+                table: TableRef::new(&captures.take_identifier("Name")?),
+                if_exists: captures.take_optional("IfExists")?.is_some(),
+            },
+        })
     }
 
-    fn execute(&self, database: &mut crate::database::Database) -> Result<QuerySuccess, DbErr> {
-        if let Some(Token::Identifier(table_name)) = self.data.get("Name") {
-            match database.delete_table(table_name) {
-                Ok(_table) => Ok(QuerySuccess::new()),
-                Err(err) => Err(err),
-            }
-        } else {
-            Err(DbErr::Generic(String::from("Bad name param")))
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        if self.statement.if_exists && database.get_table(&self.statement.table.name).is_none() {
+            return Ok(QuerySuccess::Done);
+        }
+
+        match database.delete_table(&self.statement.table.name) {
+            Ok(_table) => Ok(QuerySuccess::Done),
+            Err(err) => Err(err),
         }
     }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::DeleteTable(self.statement.clone()))
+    }
 }
 
 #[cfg(test)]
@@ -41,4 +52,16 @@ mod tests {
         let mut database = Database::new();
         assert!(query_parser::execute_query(&mut database, "DELETE TABLE users;").is_err());
     }
+
+    #[test]
+    fn drop_table_if_exists() {
+        let mut database = Database::new();
+        database.create_table("users", Vec::new()).unwrap();
+
+        assert!(query_parser::execute_query(&mut database, "DROP TABLE users;").is_ok());
+        assert!(database.get_table("users").is_none());
+        assert!(query_parser::execute_query(&mut database, "DROP TABLE users;").is_err());
+        assert!(query_parser::execute_query(&mut database, "DROP TABLE IF EXISTS users;").is_ok());
+        assert!(query_parser::execute_query(&mut database, "DELETE TABLE IF EXISTS users;").is_ok());
+    }
 }
@@ -0,0 +1,421 @@
+use crate::{
+    ast::{BinaryOperator, ColumnRef, Expr, Literal, UnaryOperator},
+    tokenizer::{Placeholder, Token},
+};
+
+use super::{parse_steps::ParseFailure, token_cursor::TokenCursor};
+
+// Binding powers, from loosest to tightest. Infix operators bind their left operand with the even power
+// and their right operand with the odd one above it, which makes every operator left associative.
+pub(crate) const OR: u8 = 2;
+pub(crate) const AND: u8 = 4;
+pub(crate) const NOT: u8 = 6;
+pub(crate) const IS: u8 = 8;
+pub(crate) const COMPARISON: u8 = 10;
+pub(crate) const BETWEEN_IN: u8 = 12;
+pub(crate) const CONCAT: u8 = 14;
+pub(crate) const ADDITIVE: u8 = 16;
+pub(crate) const MULTIPLICATIVE: u8 = 18;
+pub(crate) const SIGN: u8 = 20;
+
+fn infix_operator(token: &Token) -> Option<(BinaryOperator, u8)> {
+    let operator = match token {
+        Token::Or => BinaryOperator::Or,
+        Token::And => BinaryOperator::And,
+        Token::Equals => BinaryOperator::Equals,
+        Token::NotEquals => BinaryOperator::NotEquals,
+        Token::LessThan => BinaryOperator::LessThan,
+        Token::LessOrEqual => BinaryOperator::LessOrEqual,
+        Token::GreaterThan => BinaryOperator::GreaterThan,
+        Token::GreaterOrEqual => BinaryOperator::GreaterOrEqual,
+        Token::Concat => BinaryOperator::Concat,
+        Token::Plus => BinaryOperator::Plus,
+        Token::Minus => BinaryOperator::Minus,
+        Token::Asterisk => BinaryOperator::Multiply,
+        Token::Slash => BinaryOperator::Divide,
+        Token::Percent => BinaryOperator::Modulo,
+        _ => return None,
+    };
+
+    Some((operator, binding_power(operator)))
+}
+
+/// How tightly an infix operator binds its operands
+pub(crate) fn binding_power(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => OR,
+        BinaryOperator::And => AND,
+        BinaryOperator::Equals
+        | BinaryOperator::NotEquals
+        | BinaryOperator::LessThan
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterOrEqual => COMPARISON,
+        BinaryOperator::Concat => CONCAT,
+        BinaryOperator::Plus | BinaryOperator::Minus => ADDITIVE,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => MULTIPLICATIVE,
+    }
+}
+
+/// Parses an expression starting at the cursor, consuming as many tokens as form a valid expression
+pub(crate) fn parse_expression(cursor: &mut TokenCursor) -> Result<Expr, ParseFailure> {
+    parse_expression_with_power(cursor, 0)
+}
+
+/// Parses an expression made only of operators that bind tighter than `min_power`
+fn parse_expression_with_power(cursor: &mut TokenCursor, min_power: u8) -> Result<Expr, ParseFailure> {
+    let mut left = parse_prefix(cursor)?;
+
+    while let Some(token) = cursor.peek() {
+        // `NOT` only continues an expression as part of `NOT BETWEEN` or `NOT IN`
+        let negated = *token == Token::Not && matches!(cursor.peek_nth(1), Some(Token::Between | Token::In));
+        let operator_token = if negated { cursor.peek_nth(1).unwrap() } else { token };
+
+        match operator_token {
+            Token::Is if IS >= min_power => {
+                cursor.advance();
+                let negated = cursor.eat(&Token::Not);
+                cursor.expect(&Token::Null)?;
+
+                left = Expr::IsNull {
+                    operand: Box::new(left),
+                    negated,
+                };
+            }
+            Token::Between if BETWEEN_IN >= min_power => {
+                cursor.advance();
+                if negated {
+                    cursor.advance();
+                }
+                // The bounds bind tighter than `AND`, so `a BETWEEN 1 AND 2 AND b` splits after the 2
+                let low = parse_expression_with_power(cursor, BETWEEN_IN + 1)?;
+                cursor.expect(&Token::And)?;
+                let high = parse_expression_with_power(cursor, BETWEEN_IN + 1)?;
+
+                left = Expr::Between {
+                    operand: Box::new(left),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                };
+            }
+            Token::In if BETWEEN_IN >= min_power => {
+                cursor.advance();
+                if negated {
+                    cursor.advance();
+                }
+                cursor.expect(&Token::OpenBracket)?;
+                let list = parse_expression_list(cursor)?;
+
+                left = Expr::InList {
+                    operand: Box::new(left),
+                    list,
+                    negated,
+                };
+            }
+            _ => match infix_operator(token) {
+                Some((operator, power)) if power >= min_power => {
+                    cursor.advance();
+                    let right = parse_expression_with_power(cursor, power + 1)?;
+                    left = Expr::binary(left, operator, right);
+                }
+                _ => break,
+            },
+        }
+    }
+
+    Ok(left)
+}
+
+/// Parses a literal, column, bracketed expression or prefix operator
+fn parse_prefix(cursor: &mut TokenCursor) -> Result<Expr, ParseFailure> {
+    let literal = match cursor.peek() {
+        Some(Token::Null) => Literal::Null,
+        Some(Token::True) => Literal::Boolean(true),
+        Some(Token::False) => Literal::Boolean(false),
+        Some(Token::Integer(value)) => Literal::Integer(*value),
+        Some(Token::Decimal(value) | Token::Scientific(value)) => Literal::Float(*value),
+        Some(Token::StringLiteral(value)) => Literal::String(value.clone()),
+        Some(Token::BlobLiteral(value)) => Literal::Blob(value.clone()),
+        Some(Token::Identifier(_)) => return parse_column(cursor),
+        // Prepared statements number every placeholder before parsing, whatever its style
+        Some(Token::Placeholder(Placeholder::Numbered(number))) => {
+            let idx = number - 1;
+            cursor.advance();
+            return Ok(Expr::Parameter(idx));
+        }
+        Some(Token::OpenBracket) => {
+            cursor.advance();
+            let expr = parse_expression(cursor)?;
+            cursor.expect(&Token::CloseBracket)?;
+            return Ok(expr);
+        }
+        Some(token @ (Token::Not | Token::Plus | Token::Minus)) => {
+            let (operator, power) = match token {
+                Token::Not => (UnaryOperator::Not, NOT),
+                Token::Plus => (UnaryOperator::Plus, SIGN),
+                _ => (UnaryOperator::Minus, SIGN),
+            };
+
+            cursor.advance();
+            let operand = parse_expression_with_power(cursor, power)?;
+            return Ok(Expr::unary(operator, operand));
+        }
+        _ => return Err(cursor.failure(vec![String::from("expression")])),
+    };
+
+    cursor.advance();
+    Ok(Expr::Literal(literal))
+}
+
+/// `column` or `table.column`
+fn parse_column(cursor: &mut TokenCursor) -> Result<Expr, ParseFailure> {
+    let first = cursor.expect_identifier()?;
+
+    if cursor.eat(&Token::Dot) {
+        let column = cursor.expect_identifier()?;
+        Ok(Expr::Column(ColumnRef {
+            table: Some(first),
+            column,
+        }))
+    } else {
+        Ok(Expr::Column(ColumnRef {
+            table: None,
+            column: first,
+        }))
+    }
+}
+
+/// The items of a bracketed list, after its opening bracket, up to and including the closing bracket
+pub(crate) fn parse_expression_list(cursor: &mut TokenCursor) -> Result<Vec<Expr>, ParseFailure> {
+    let mut list = vec![parse_expression(cursor)?];
+
+    while !cursor.eat(&Token::CloseBracket) {
+        if !cursor.eat(&Token::Comma) {
+            return Err(cursor.failure(vec![Token::Comma.describe(), Token::CloseBracket.describe()]));
+        }
+        list.push(parse_expression(cursor)?);
+    }
+
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{BinaryOperator, ColumnRef, Expr, Literal, UnaryOperator},
+        queries::{expression_parser::parse_expression, parse_steps::ParseFailure, token_cursor::TokenCursor},
+        tokenizer::Tokenizer,
+    };
+
+    fn parse(expression: &str) -> Expr {
+        let tokens = Tokenizer::new().tokenize_spanned(expression).unwrap();
+        let mut cursor = TokenCursor::new(&tokens);
+        let expr = parse_expression(&mut cursor).unwrap();
+
+        assert!(cursor.is_at_end(), "Didn't consume all of {}", expression);
+        expr
+    }
+
+    fn parse_failure(expression: &str) -> ParseFailure {
+        let tokens = Tokenizer::new().tokenize_spanned(expression).unwrap();
+        parse_expression(&mut TokenCursor::new(&tokens)).unwrap_err()
+    }
+
+    fn column(name: &str) -> Expr {
+        Expr::column(name)
+    }
+
+    fn int(value: i64) -> Expr {
+        Expr::integer(value)
+    }
+
+    fn binary(left: Expr, operator: BinaryOperator, right: Expr) -> Expr {
+        Expr::binary(left, operator, right)
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(int(42), parse("42"));
+        assert_eq!(Expr::Literal(Literal::Float(1.5)), parse("1.5"));
+        assert_eq!(Expr::Literal(Literal::String(String::from("it's"))), parse("'it''s'"));
+        assert_eq!(Expr::Literal(Literal::Null), parse("NULL"));
+        assert_eq!(Expr::Literal(Literal::Boolean(false)), parse("false"));
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(column("age"), parse("age"));
+        assert_eq!(
+            Expr::Column(ColumnRef {
+                table: Some(String::from("users")),
+                column: String::from("age")
+            }),
+            parse("users.age")
+        );
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_eq!(
+            binary(int(1), BinaryOperator::Plus, binary(int(2), BinaryOperator::Multiply, int(3))),
+            parse("1 + 2 * 3")
+        );
+        assert_eq!(
+            binary(binary(int(1), BinaryOperator::Plus, int(2)), BinaryOperator::Multiply, int(3)),
+            parse("(1 + 2) * 3")
+        );
+    }
+
+    #[test]
+    fn left_associative() {
+        assert_eq!(
+            binary(binary(int(1), BinaryOperator::Minus, int(2)), BinaryOperator::Minus, int(3)),
+            parse("1 - 2 - 3")
+        );
+        assert_eq!(
+            binary(binary(int(8), BinaryOperator::Divide, int(4)), BinaryOperator::Modulo, int(3)),
+            parse("8 / 4 % 3")
+        );
+    }
+
+    #[test]
+    fn unary_operators() {
+        assert_eq!(
+            binary(Expr::unary(UnaryOperator::Minus, int(1)), BinaryOperator::Plus, int(2)),
+            parse("-1 + 2")
+        );
+        assert_eq!(
+            Expr::unary(UnaryOperator::Not, binary(column("a"), BinaryOperator::Equals, int(1))),
+            parse("NOT a = 1")
+        );
+        assert_eq!(
+            Expr::unary(UnaryOperator::Minus, Expr::unary(UnaryOperator::Plus, int(1))),
+            parse("- + 1")
+        );
+    }
+
+    #[test]
+    fn logical_precedence() {
+        // AND binds tighter than OR, and comparisons tighter than both
+        assert_eq!(
+            binary(
+                binary(column("a"), BinaryOperator::Equals, int(1)),
+                BinaryOperator::Or,
+                binary(
+                    binary(column("b"), BinaryOperator::LessThan, int(2)),
+                    BinaryOperator::And,
+                    binary(column("c"), BinaryOperator::GreaterOrEqual, int(3))
+                )
+            ),
+            parse("a = 1 OR b < 2 AND c >= 3")
+        );
+    }
+
+    #[test]
+    fn concat_binds_looser_than_arithmetic() {
+        assert_eq!(
+            binary(
+                binary(column("a"), BinaryOperator::Concat, binary(int(1), BinaryOperator::Plus, int(2))),
+                BinaryOperator::NotEquals,
+                column("b")
+            ),
+            parse("a || 1 + 2 <> b")
+        );
+    }
+
+    #[test]
+    fn is_null() {
+        assert_eq!(
+            binary(
+                Expr::IsNull {
+                    operand: Box::new(column("a")),
+                    negated: false
+                },
+                BinaryOperator::And,
+                Expr::IsNull {
+                    operand: Box::new(binary(column("b"), BinaryOperator::Plus, int(1))),
+                    negated: true
+                }
+            ),
+            parse("a IS NULL AND b + 1 IS NOT NULL")
+        );
+    }
+
+    #[test]
+    fn between() {
+        assert_eq!(
+            binary(
+                Expr::Between {
+                    operand: Box::new(column("a")),
+                    low: Box::new(int(1)),
+                    high: Box::new(binary(int(2), BinaryOperator::Plus, int(3))),
+                    negated: true
+                },
+                BinaryOperator::And,
+                column("b")
+            ),
+            parse("a NOT BETWEEN 1 AND 2 + 3 AND b")
+        );
+    }
+
+    #[test]
+    fn in_list() {
+        assert_eq!(
+            Expr::InList {
+                operand: Box::new(column("a")),
+                list: vec![int(1), binary(int(2), BinaryOperator::Multiply, int(3))],
+                negated: false
+            },
+            parse("a IN (1, 2 * 3)")
+        );
+        assert_eq!(
+            Expr::unary(
+                UnaryOperator::Not,
+                Expr::InList {
+                    operand: Box::new(column("a")),
+                    list: vec![int(1)],
+                    negated: true
+                }
+            ),
+            parse("NOT a NOT IN (1)")
+        );
+    }
+
+    #[test]
+    fn stops_at_unrelated_token() {
+        let tokens = Tokenizer::new().tokenize_spanned("a + 1, b").unwrap();
+        let mut cursor = TokenCursor::new(&tokens);
+
+        assert_eq!(
+            binary(column("a"), BinaryOperator::Plus, int(1)),
+            parse_expression(&mut cursor).unwrap()
+        );
+        assert_eq!(3, cursor.position());
+    }
+
+    #[test]
+    fn failures() {
+        assert_eq!(
+            ParseFailure {
+                position: 2,
+                expected: vec![String::from("expression")],
+                context: Some(String::from("`*`")),
+                unclosed: None
+            },
+            parse_failure("1 * ")
+        );
+        assert_eq!(
+            ParseFailure {
+                position: 4,
+                expected: vec![String::from("`)`")],
+                context: Some(String::from("integer")),
+                unclosed: None
+            },
+            parse_failure("(1 + 2 3")
+        );
+        assert_eq!(vec![String::from("`NULL`")], parse_failure("a IS 1").expected);
+        assert_eq!(vec![String::from("`AND`")], parse_failure("a BETWEEN 1 OR 2").expected);
+        assert_eq!(vec![String::from("`,`"), String::from("`)`")], parse_failure("a IN (1 2)").expected);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    ast::{Expr, Insert, Statement, TableRef},
+    database::{Database, Table},
+    errors::DbErr,
+    evaluator::Evaluator,
+    field_value::FieldValue,
+    row_store::RowId,
+};
+
+use super::{parse_steps::Captures, prepared_statement, Query, QuerySuccess};
+
+pub(crate) struct InsertQuery {
+    statement: Insert,
+}
+
+impl Query for InsertQuery {
+    fn new(mut captures: Captures) -> Result<InsertQuery, DbErr> {
+        let columns = match captures.take_optional("Columns")? {
+            Some(mut captured) => Some(
+                captured
+                    .take_repeated("Columns")?
+                    .into_iter()
+                    .map(|mut column| column.take_identifier("Name"))
+                    .collect::<Result<Vec<String>, DbErr>>()?,
+            ),
+            None => None,
+        };
+
+        let rows = captures
+            .take_repeated("Rows")?
+            .into_iter()
+            .map(|mut row| {
+                row.take_repeated("Values")?
+                    .into_iter()
+                    .map(|mut value| value.take_expression("Value"))
+                    .collect::<Result<Vec<Expr>, DbErr>>()
+            })
+            .collect::<Result<Vec<Vec<Expr>>, DbErr>>()?;
+
+        Ok(InsertQuery {
+            statement: Insert {
+                table: TableRef::new(&captures.take_identifier("Name")?),
+                columns,
+                rows,
+            },
+        })
+    }
+
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        let table = database.get_table_mut(&self.statement.table.name).ok_or(DbErr::TableNotExists)?;
+
+        let targets = self.target_columns(table)?;
+        let rows = self
+            .statement
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row_idx, row)| InsertQuery::build_row(table, &targets, row_idx, row))
+            .collect::<Result<Vec<Vec<FieldValue>>, DbErr>>()?;
+
+        let mut inserted: Vec<RowId> = Vec::new();
+        for values in rows {
+            match table.insert_row(values) {
+                Ok(row_id) => inserted.push(row_id),
+                Err(err) => {
+                    // One bad row inserts nothing, so the rows before it are taken out again
+                    for row_id in inserted.into_iter().rev() {
+                        table.delete_row(row_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(QuerySuccess::RowsAffected(inserted.len()))
+    }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::Insert(self.statement.clone()))
+    }
+
+    fn bind(&self, database: &Database, arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        let table = database.get_table(&self.statement.table.name).ok_or(DbErr::TableNotExists)?;
+        let targets = self.target_columns(table)?;
+
+        let rows = self
+            .statement
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(position, expr)| match targets.get(position) {
+                        Some(idx) => prepared_statement::bind_to_column(expr, &table.columns[*idx], arguments),
+                        // Rows with too many values are reported when the query runs
+                        None => Ok(expr.bind(arguments)),
+                    })
+                    .collect::<Result<Vec<Expr>, DbErr>>()
+            })
+            .collect::<Result<Vec<Vec<Expr>>, DbErr>>()?;
+
+        Ok(Box::new(InsertQuery {
+            statement: Insert {
+                rows,
+                ..self.statement.clone()
+            },
+        }))
+    }
+}
+
+impl InsertQuery {
+    /// The position of the column each value of a row goes into
+    fn target_columns(&self, table: &Table) -> Result<Vec<usize>, DbErr> {
+        let names = match &self.statement.columns {
+            Some(names) => names,
+            None => return Ok((0..table.columns.len()).collect()),
+        };
+
+        let mut targets: Vec<usize> = Vec::new();
+        for name in names {
+            let idx = table
+                .columns
+                .iter()
+                .position(|column| column.column_name == *name)
+                .ok_or_else(|| DbErr::ColumnNotExists(name.clone()))?;
+
+            if targets.contains(&idx) {
+                return Err(DbErr::Generic(format!("Column {} is listed more than once", name)));
+            }
+            targets.push(idx);
+        }
+
+        Ok(targets)
+    }
+
+    /// Evaluates a row's values, fills in the columns it leaves out, and converts every value to its column's type
+    fn build_row(table: &Table, targets: &[usize], row_idx: usize, row: &[Expr]) -> Result<Vec<FieldValue>, DbErr> {
+        if row.len() != targets.len() {
+            return Err(DbErr::Generic(format!(
+                "Row {} has {} values, expected {}",
+                row_idx + 1,
+                row.len(),
+                targets.len()
+            )));
+        }
+
+        let evaluator = Evaluator::constant();
+        let mut values: Vec<Option<FieldValue>> = vec![None; table.columns.len()];
+        for (expr, idx) in row.iter().zip(targets) {
+            values[*idx] = Some(evaluator.evaluate(expr, &[])?);
+        }
+
+        table
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| {
+                let value = match (value, &column.default) {
+                    (Some(value), _) => value,
+                    (None, Some(default)) => evaluator.evaluate(default, &[])?,
+                    (None, None) => FieldValue::Null,
+                };
+                column.column_type.coerce(value, &column.column_name)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::{
+            query_parser::{self, error_message},
+            QuerySuccess,
+        },
+    };
+
+    fn database_with(definition: &str) -> Database {
+        let mut database = Database::new();
+        query_parser::execute_query(&mut database, definition).unwrap();
+        database
+    }
+
+    fn rows(database: &Database, table: &str) -> Vec<Vec<FieldValue>> {
+        database
+            .get_table(table)
+            .unwrap()
+            .rows
+            .entries()
+            .map(|row| row.fields.clone())
+            .collect()
+    }
+
+    #[test]
+    fn insert_rows() {
+        let mut database = database_with("CREATE TABLE users (id INTEGER, name TEXT, score DOUBLE);");
+
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(2)),
+            query_parser::execute_query(&mut database, "INSERT INTO users VALUES (1, 'ann', 2), (2, 'bob', 1.5 * 3);")
+        );
+        assert_eq!(
+            vec![
+                vec![
+                    FieldValue::Integer(1),
+                    FieldValue::Text(String::from("ann")),
+                    FieldValue::Double(2.0)
+                ],
+                vec![
+                    FieldValue::Integer(2),
+                    FieldValue::Text(String::from("bob")),
+                    FieldValue::Double(4.5)
+                ],
+            ],
+            rows(&database, "users")
+        );
+    }
+
+    #[test]
+    fn omitted_columns_take_defaults() {
+        let mut database = database_with("CREATE TABLE t (a INTEGER, b INTEGER DEFAULT 2 * 3, c TEXT);");
+
+        assert!(query_parser::execute_query(&mut database, "INSERT INTO t (c, a) VALUES ('x', 1);").is_ok());
+        assert_eq!(
+            vec![vec![
+                FieldValue::Integer(1),
+                FieldValue::Integer(6),
+                FieldValue::Text(String::from("x"))
+            ]],
+            rows(&database, "t")
+        );
+
+        assert!(query_parser::execute_query(&mut database, "INSERT INTO t (b) VALUES (NULL);").is_ok());
+        assert_eq!(vec![FieldValue::Null, FieldValue::Null, FieldValue::Null], rows(&database, "t")[1]);
+    }
+
+    #[test]
+    fn invalid_rows() {
+        let mut database = database_with("CREATE TABLE t (a SMALLINT, b TEXT);");
+
+        assert_eq!(
+            DbErr::Generic(String::from("Row 2 has 1 values, expected 2")),
+            query_parser::execute_query(&mut database, "INSERT INTO t VALUES (1, 'x'), (2);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TypeMismatch(String::from("Can't store INTEGER in TEXT column b")),
+            query_parser::execute_query(&mut database, "INSERT INTO t (b) VALUES (3);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ArithmeticError(String::from("100000 is out of range for SMALLINT column a")),
+            query_parser::execute_query(&mut database, "INSERT INTO t (a) VALUES (100000);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("c")),
+            query_parser::execute_query(&mut database, "INSERT INTO t (a, c) VALUES (1, 2);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Generic(String::from("Column a is listed more than once")),
+            query_parser::execute_query(&mut database, "INSERT INTO t (a, a) VALUES (1, 2);").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("a")),
+            query_parser::execute_query(&mut database, "INSERT INTO t VALUES (a, 'x');").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TableNotExists,
+            query_parser::execute_query(&mut database, "INSERT INTO missing VALUES (1);").unwrap_err()
+        );
+        assert!(rows(&database, "t").is_empty());
+    }
+
+    #[test]
+    fn one_bad_row_inserts_nothing() {
+        let mut database = database_with("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL);");
+        query_parser::execute_query(&mut database, "INSERT INTO t VALUES (1, 'a');").unwrap();
+
+        assert_eq!(
+            DbErr::PrimaryKeyViolation {
+                constraint: String::from("t_pkey")
+            },
+            query_parser::execute_query(&mut database, "INSERT INTO t VALUES (2, 'b'), (3, 'c'), (2, 'd');").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::NotNullViolation {
+                column: String::from("name")
+            },
+            query_parser::execute_query(&mut database, "INSERT INTO t VALUES (2, 'b'), (3, NULL);").unwrap_err()
+        );
+        assert_eq!(1, rows(&database, "t").len());
+
+        // The rolled back keys are free again
+        assert!(query_parser::execute_query(&mut database, "INSERT INTO t VALUES (2, 'b'), (3, 'c');").is_ok());
+        assert_eq!(3, rows(&database, "t").len());
+    }
+
+    #[test]
+    fn malformed_inserts() {
+        assert_eq!("expected `INTO` after `INSERT` at 1:8", error_message("INSERT t VALUES (1);"));
+        assert_eq!(
+            "expected one of `(` or `VALUES` after table name at 1:15",
+            error_message("INSERT INTO t VALUE (1);")
+        );
+        assert_eq!(
+            "expected one of `,` or `;` after row at 1:26",
+            error_message("INSERT INTO t VALUES (1) (2);")
+        );
+    }
+}
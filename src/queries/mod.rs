@@ -1,24 +1,60 @@
 pub(crate) mod create_table_query;
+pub(crate) mod delete_query;
 pub(crate) mod delete_table_query;
-pub(crate) mod parse_steps;
-pub(crate) mod query_builder;
-pub(crate) mod query_parser;
+pub(crate) mod expression_parser;
+pub(crate) mod insert_query;
+pub mod parse_steps;
+pub mod prepared_statement;
+pub mod query_builder;
+pub mod query_parser;
+pub(crate) mod result_set;
+pub(crate) mod select_query;
+pub(crate) mod sort;
+pub(crate) mod statement_registry;
+pub mod token_cursor;
+pub(crate) mod update_query;
 
-use std::collections::HashMap;
+use std::fmt;
 
-use crate::{database::Database, errors::DbErr, tokenizer::Token};
+use crate::{ast::Statement, database::Database, errors::DbErr, field_value::FieldValue};
 
-pub(crate) struct QuerySuccess {}
+use self::{parse_steps::Captures, result_set::ResultSet};
 
-impl QuerySuccess {
-    pub fn new() -> QuerySuccess {
-        QuerySuccess {}
+/// What a statement that ran successfully produced
+#[derive(Debug, PartialEq)]
+pub enum QuerySuccess {
+    /// The statement changed the database without returning anything
+    Done,
+    /// The rows a query read
+    Rows(ResultSet),
+    /// The number of rows a statement changed
+    RowsAffected(usize),
+}
+
+impl fmt::Display for QuerySuccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuerySuccess::Done => write!(f, "Done"),
+            QuerySuccess::Rows(result_set) => write!(f, "{}", result_set),
+            QuerySuccess::RowsAffected(1) => write!(f, "1 row affected"),
+            QuerySuccess::RowsAffected(count) => write!(f, "{} rows affected", count),
+        }
     }
 }
 
-pub(crate) trait Query {
-    fn new(data: HashMap<String, Token>) -> Self
+pub trait Query {
+    /// Builds the query from what its grammar captured
+    fn new(captures: Captures) -> Result<Self, DbErr>
     where
         Self: Sized;
     fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr>;
+    /// The syntax tree of the statement, for queries that have one
+    fn statement(&self) -> Option<Statement> {
+        None
+    }
+    /// A copy of the query with `arguments` in place of its parameters. Arguments stored in a column are
+    /// checked against the column's type here, rather than when the query runs.
+    fn bind(&self, _database: &Database, _arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        Err(DbErr::Generic(String::from("Statement doesn't take parameters")))
+    }
 }
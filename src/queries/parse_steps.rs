@@ -1,101 +1,968 @@
-use std::{
-    collections::HashMap,
-    mem::{self},
+use std::collections::HashMap;
+
+use crate::{
+    ast::Expr,
+    errors::{DbErr, SyntaxError},
+    tokenizer::{Span, SpannedToken, Token},
 };
 
-use crate::{errors::DbErr, tokenizer::Token};
+use super::{expression_parser, token_cursor::TokenCursor};
+
+/// A recursive descent parser that a grammar hands control to for structures steps can't express
+pub type SubParser = fn(&mut TokenCursor) -> Result<Capture, ParseFailure>;
 
 enum Step {
-    UnnamedStep(Token),
-    NamedStep(Token, String),
+    Token(Token),
+    /// A word that isn't one of the tokenizer's keywords, matched case insensitively against an identifier
+    Keyword(String),
+    NamedToken(Token, String),
     NamedStream(Token, Token, String),
+    Expression(String),
+    Parser(SubParser, String),
+    /// A grammar that is skipped if its first step doesn't match
+    Optional(ParseSteps, String),
+    /// The first of several grammars that matches
+    OneOf(Vec<ParseSteps>, String),
+    /// A grammar matched at least once, with a separator token between each match
+    Repeated(ParseSteps, Token, String),
+    /// A grammar matched as many times in a row as it matches, possibly never
+    Many(ParseSteps, String),
+}
+
+impl Step {
+    /// What the step refers to in error messages, unless it was given a description
+    fn default_description(&self) -> String {
+        match self {
+            Step::Token(token) | Step::NamedToken(token, _) => token.describe(),
+            Step::Keyword(keyword) => format!("`{}`", keyword.to_uppercase()),
+            Step::NamedStream(_, end_token, _) => end_token.describe(),
+            Step::Expression(_) => String::from("expression"),
+            Step::Parser(_, name) | Step::Optional(_, name) | Step::OneOf(_, name) | Step::Repeated(_, _, name) | Step::Many(_, name) => {
+                name.clone()
+            }
+        }
+    }
+
+    /// The name the step's capture is stored under
+    fn name(&self) -> Option<&str> {
+        match self {
+            Step::Token(_) | Step::Keyword(_) => None,
+            Step::NamedToken(_, name)
+            | Step::NamedStream(_, _, name)
+            | Step::Expression(name)
+            | Step::Parser(_, name)
+            | Step::Optional(_, name)
+            | Step::OneOf(_, name)
+            | Step::Repeated(_, _, name)
+            | Step::Many(_, name) => Some(name),
+        }
+    }
+}
+
+struct DescribedStep {
+    step: Step,
+    description: Option<String>,
+}
+
+/// A value captured by a named step
+#[derive(Debug, PartialEq, Clone)]
+pub enum Capture {
+    Token(Token),
+    Tokens(Vec<Token>),
+    Expression(Expr),
+    /// What an optional grammar captured, if it was present
+    Optional(Option<Captures>),
+    /// The index of the alternative that matched, and what it captured
+    Choice(usize, Captures),
+    /// What each repetition captured
+    Repeated(Vec<Captures>),
+}
+
+/// The values captured by the named steps of a grammar
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Captures {
+    values: HashMap<String, Capture>,
+}
+
+impl Captures {
+    pub(crate) fn insert(&mut self, name: &str, capture: Capture) {
+        self.values.insert(name.to_owned(), capture);
+    }
+
+    /// Removes a capture. Missing captures mean the grammar and the query disagree, so they are reported as errors.
+    pub fn take(&mut self, name: &str) -> Result<Capture, DbErr> {
+        self.values
+            .remove(name)
+            .ok_or_else(|| DbErr::Generic(format!("Nothing was captured for {}", name)))
+    }
+
+    fn mismatch(name: &str, expected: &str, capture: Capture) -> DbErr {
+        DbErr::Generic(format!("Expected {} for {}, got {:?}", expected, name, capture))
+    }
+
+    pub fn take_identifier(&mut self, name: &str) -> Result<String, DbErr> {
+        match self.take(name)? {
+            Capture::Token(Token::Identifier(identifier)) => Ok(identifier),
+            capture => Err(Captures::mismatch(name, "an identifier", capture)),
+        }
+    }
+
+    pub fn take_integer(&mut self, name: &str) -> Result<i64, DbErr> {
+        match self.take(name)? {
+            Capture::Token(Token::Integer(value)) => Ok(value),
+            capture => Err(Captures::mismatch(name, "an integer", capture)),
+        }
+    }
+
+    pub fn take_expression(&mut self, name: &str) -> Result<Expr, DbErr> {
+        match self.take(name)? {
+            Capture::Expression(expr) => Ok(expr),
+            capture => Err(Captures::mismatch(name, "an expression", capture)),
+        }
+    }
+
+    pub fn take_optional(&mut self, name: &str) -> Result<Option<Captures>, DbErr> {
+        match self.take(name)? {
+            Capture::Optional(captures) => Ok(captures),
+            capture => Err(Captures::mismatch(name, "an optional grammar", capture)),
+        }
+    }
+
+    pub fn take_choice(&mut self, name: &str) -> Result<(usize, Captures), DbErr> {
+        match self.take(name)? {
+            Capture::Choice(idx, captures) => Ok((idx, captures)),
+            capture => Err(Captures::mismatch(name, "a choice", capture)),
+        }
+    }
+
+    pub fn take_repeated(&mut self, name: &str) -> Result<Vec<Captures>, DbErr> {
+        match self.take(name)? {
+            Capture::Repeated(repetitions) => Ok(repetitions),
+            capture => Err(Captures::mismatch(name, "a repetition", capture)),
+        }
+    }
 }
 
-pub(crate) struct ParseSteps {
-    steps: Vec<Step>,
+/// What a grammar that matched leaves behind for the steps that follow it
+struct Matched {
+    captures: Captures,
+    /// Why an optional part at the end wasn't matched. If the next step fails at the same
+    /// position, both are reported, e.g. "expected one of `,` or `)`".
+    skipped: Option<ParseFailure>,
+    /// Describes the last step that consumed tokens, for the "after ..." part of the next failure
+    context: Option<String>,
+}
+
+/// What a single step that matched leaves behind
+struct StepMatch {
+    capture: Option<Capture>,
+    skipped: Option<ParseFailure>,
+    context: Option<String>,
+}
+
+impl StepMatch {
+    fn new(capture: Option<Capture>) -> StepMatch {
+        StepMatch {
+            capture,
+            skipped: None,
+            context: None,
+        }
+    }
+}
+
+pub struct ParseSteps {
+    steps: Vec<DescribedStep>,
+}
+
+/// Why a grammar stopped matching. Failures from every builder are merged into a single syntax error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseFailure {
+    /// Index of the token that didn't match, `tokens.len()` if the statement ended early
+    pub position: usize,
+    /// Descriptions of the tokens that would have been accepted, such as "`,`" or "identifier"
+    pub expected: Vec<String>,
+    /// Description of what was matched right before the failure, such as "column type"
+    pub context: Option<String>,
+    /// Index of an opening token that was never closed, which explains the failure better than the token
+    /// it happened at
+    pub unclosed: Option<usize>,
+}
+
+impl ParseFailure {
+    /// Combines two failures, keeping whichever got further. At the same position, everything
+    /// that either of them expected is kept.
+    pub(crate) fn merge(self, other: ParseFailure) -> ParseFailure {
+        if other.position > self.position {
+            return other;
+        } else if other.position < self.position {
+            return self;
+        }
+
+        let mut expected = self.expected;
+        for description in other.expected {
+            if !expected.contains(&description) {
+                expected.push(description);
+            }
+        }
+
+        ParseFailure {
+            position: self.position,
+            expected,
+            context: if self.context == other.context { self.context } else { None },
+            unclosed: self.unclosed.or(other.unclosed),
+        }
+    }
+
+    /// Builds an error such as "expected one of `,` or `)` after column type", located at the failing token.
+    /// A failure caused by a token that was never closed points at that token instead.
+    pub(crate) fn into_error(self, query: &str, tokens: &[SpannedToken]) -> DbErr {
+        if let Some(opening) = self.unclosed.and_then(|idx| tokens.get(idx)) {
+            return DbErr::Syntax(SyntaxError::new(format!("unmatched {}", opening.token.describe()), opening.span));
+        }
+
+        let mut message = match self.expected.as_slice() {
+            [] => String::from("unexpected token"),
+            [only] => format!("expected {}", only),
+            [rest @ .., last] => format!("expected one of {} or {}", rest.join(", "), last),
+        };
+
+        if let Some(context) = &self.context {
+            message.push_str(&format!(" after {}", context));
+        }
+
+        let span = match tokens.get(self.position) {
+            Some(token) => token.span,
+            None => {
+                message.push_str(", but the statement ended");
+                Span::end_of(query)
+            }
+        };
+
+        DbErr::Syntax(SyntaxError::new(message, span))
+    }
+}
+
+impl Default for ParseSteps {
+    fn default() -> ParseSteps {
+        ParseSteps::new()
+    }
 }
 
 impl ParseSteps {
-    pub(crate) fn new() -> ParseSteps {
+    pub fn new() -> ParseSteps {
         ParseSteps { steps: Vec::new() }
     }
 
-    pub(crate) fn add_named_step(mut self, token: Token, name: &str) -> Self {
-        self.steps.push(Step::NamedStep(token, name.to_owned()));
-        self
+    pub fn add_named_step(self, token: Token, name: &str) -> Self {
+        self.push(Step::NamedToken(token, name.to_owned()))
+    }
+
+    pub fn add_step(self, token: Token) -> Self {
+        self.push(Step::Token(token))
+    }
+
+    /// Matches a word that isn't a keyword for the tokenizer, so custom statements can use their own
+    pub fn add_keyword(self, keyword: &str) -> Self {
+        self.push(Step::Keyword(keyword.to_owned()))
+    }
+
+    pub fn add_token_capture_stream(self, start_token: Token, end_token: Token, step_name: String) -> Self {
+        self.push(Step::NamedStream(start_token, end_token, step_name))
+    }
+
+    pub fn add_expression(self, name: &str) -> Self {
+        self.push(Step::Expression(name.to_owned()))
+    }
+
+    /// Hands control to `parser`, capturing whatever it returns under `name`
+    pub fn add_parser(self, parser: SubParser, name: &str) -> Self {
+        self.push(Step::Parser(parser, name.to_owned()))
+    }
+
+    /// Matches `steps` if its first step matches. Fails if it matches partially.
+    pub fn add_optional(self, steps: ParseSteps, name: &str) -> Self {
+        self.push(Step::Optional(steps, name.to_owned()))
+    }
+
+    /// Matches the first of `alternatives` that matches completely
+    pub fn add_one_of(self, alternatives: Vec<ParseSteps>, name: &str) -> Self {
+        self.push(Step::OneOf(alternatives, name.to_owned()))
     }
 
-    pub(crate) fn add_step(mut self, token: Token) -> Self {
-        self.steps.push(Step::UnnamedStep(token));
+    /// Matches `steps` one or more times, with `separator` between each repetition
+    pub fn add_repeated(self, steps: ParseSteps, separator: Token, name: &str) -> Self {
+        self.push(Step::Repeated(steps, separator, name.to_owned()))
+    }
+
+    /// Matches `steps` repeatedly until their first step no longer matches. Fails if a repetition matches partially.
+    pub fn add_many(self, steps: ParseSteps, name: &str) -> Self {
+        self.push(Step::Many(steps, name.to_owned()))
+    }
+
+    /// Names the previously added step in error messages, e.g. "expected `(` after table name"
+    pub fn described(mut self, description: &str) -> Self {
+        if let Some(last) = self.steps.last_mut() {
+            last.description = Some(description.to_owned());
+        }
         self
     }
 
-    pub(crate) fn add_token_capture_stream(mut self, start_token: Token, end_token: Token, step_name: String) -> Self {
-        self.steps.push(Step::NamedStream(start_token, end_token, step_name));
+    fn push(mut self, step: Step) -> Self {
+        self.steps.push(DescribedStep { step, description: None });
         self
     }
 
-    pub(crate) fn parse(&self, tokens: &Vec<Token>) -> Result<HashMap<String, Token>, (DbErr, usize)> {
-        let mut map: HashMap<String, Token> = HashMap::new();
-        let mut token_idx = 0;
+    /// Matches the steps against the tokens, which must all be consumed
+    pub(crate) fn parse(&self, tokens: &[SpannedToken]) -> Result<Captures, ParseFailure> {
+        let mut cursor = TokenCursor::new(tokens);
+        let matched = self.parse_from(&mut cursor)?;
 
-        for val in self.steps.iter() {
-            let token = tokens.get(token_idx);
-            if token.is_none() {
-                return Err((DbErr::Generic(String::from("Unexpected end of token stream")), token_idx));
-            }
+        if !cursor.is_at_end() {
+            let leftover = ParseFailure {
+                context: matched.context,
+                ..cursor.failure(vec![String::from("end of statement")])
+            };
+
+            return Err(match matched.skipped {
+                Some(skipped) => skipped.merge(leftover),
+                None => leftover,
+            });
+        }
 
-            let token = token.unwrap();
+        Ok(matched.captures)
+    }
 
-            token_idx += 1;
+    fn parse_from(&self, cursor: &mut TokenCursor) -> Result<Matched, ParseFailure> {
+        let mut captures = Captures::default();
+        let mut skipped: Option<ParseFailure> = None;
+        let mut context: Option<String> = None;
 
-            match val {
-                Step::UnnamedStep(step_token) => {
-                    if mem::discriminant(step_token) != mem::discriminant(token) {
-                        return Err((
-                            DbErr::Generic(format!("Unexpected token, got {:?}, expected {:?}", token, step_token,)),
-                            token_idx,
-                        ));
+        for val in &self.steps {
+            let start = cursor.position();
+            // A step that fails on its first token is reported as following the last step that matched anything
+            let start_context = match &skipped {
+                Some(failure) if failure.position == start => failure.context.clone(),
+                _ => context.clone(),
+            };
+            let at_start = |failure: ParseFailure| {
+                if failure.position == start {
+                    ParseFailure {
+                        context: start_context.clone(),
+                        ..failure
                     }
+                } else {
+                    failure
                 }
-                Step::NamedStep(step_token, step_name) => {
-                    if mem::discriminant(step_token) != mem::discriminant(token) {
-                        return Err((
-                            DbErr::Generic(format!("Unexpected token, got {:?}, expected {:?}", token, step_token,)),
-                            token_idx,
-                        ));
-                    }
+            };
+
+            let matched = match Self::parse_step(&val.step, cursor) {
+                Ok(matched) => matched,
+                Err(failure) => {
+                    let failure = at_start(failure);
+                    return Err(match skipped {
+                        Some(skipped) => skipped.merge(failure),
+                        None => failure,
+                    });
+                }
+            };
+
+            if let (Some(capture), Some(name)) = (matched.capture, val.step.name()) {
+                captures.insert(name, capture);
+            }
+            if cursor.position() > start {
+                context = val
+                    .description
+                    .clone()
+                    .or(matched.context)
+                    .or_else(|| Some(val.step.default_description()));
+            }
+            // A step that could have gone on is reported as a whole once it matched anything, so `{ constraint }`
+            // ends as "after column constraint" rather than after whichever token closed the last one
+            let step_skipped = matched.skipped.map(at_start).map(|failure| match &val.description {
+                Some(description) if cursor.position() > start && failure.position == cursor.position() => ParseFailure {
+                    context: Some(description.clone()),
+                    ..failure
+                },
+                _ => failure,
+            });
+            skipped = match (skipped, step_skipped) {
+                (Some(previous), Some(failure)) => Some(previous.merge(failure)),
+                (previous, failure) => failure.or(previous),
+            };
+        }
+
+        Ok(Matched {
+            captures,
+            skipped,
+            context,
+        })
+    }
 
-                    map.insert(step_name.clone(), token.clone());
+    fn parse_step(step: &Step, cursor: &mut TokenCursor) -> Result<StepMatch, ParseFailure> {
+        let start = cursor.position();
+
+        let capture = match step {
+            Step::Token(token) => {
+                cursor.expect(token)?;
+                return Ok(StepMatch::new(None));
+            }
+            Step::Keyword(keyword) => match cursor.peek() {
+                Some(Token::Identifier(word)) if word.eq_ignore_ascii_case(keyword) => {
+                    cursor.advance();
+                    return Ok(StepMatch::new(None));
                 }
-                Step::NamedStream(start_token, end_token, step_name) => {
-                    if mem::discriminant(token) != mem::discriminant(start_token) {
-                        return Err((
-                            DbErr::Generic(format!("Unexpected token, got {:?}, expected {:?}", token, start_token,)),
-                            token_idx,
-                        ));
+                _ => return Err(cursor.failure(vec![step.default_description()])),
+            },
+            Step::NamedToken(token, _) => Capture::Token(cursor.expect(token)?.clone()),
+            Step::NamedStream(start_token, end_token, _) => {
+                cursor.expect(start_token)?;
+
+                // Nested pairs of start and end tokens are captured, so `(a, (b, c))` is read as a whole.
+                // The start tokens still waiting for their end are kept, to point at the one that never gets it.
+                let mut token_stream: Vec<Token> = Vec::new();
+                let mut open: Vec<usize> = Vec::new();
+                loop {
+                    if open.is_empty() && cursor.eat(end_token) {
+                        break Capture::Tokens(token_stream);
                     }
 
-                    let mut found_delimiters = false;
-                    let mut token_stream: Vec<Token> = Vec::new();
-                    while let Some(token) = tokens.get(token_idx) {
-                        token_idx += 1;
+                    if cursor.check(start_token) {
+                        open.push(cursor.position());
+                    } else if cursor.check(end_token) {
+                        open.pop();
+                    }
 
-                        if mem::discriminant(token) == mem::discriminant(end_token) {
-                            found_delimiters = true;
-                            break;
-                        } else {
-                            token_stream.push(token.clone());
+                    match cursor.advance() {
+                        Some(token) => token_stream.push(token.clone()),
+                        None => {
+                            return Err(ParseFailure {
+                                unclosed: Some(open.last().copied().unwrap_or(start)),
+                                ..cursor.failure(vec![end_token.describe()])
+                            })
                         }
                     }
+                }
+            }
+            Step::Expression(_) => Capture::Expression(expression_parser::parse_expression(cursor)?),
+            Step::Parser(parser, _) => parser(cursor)?,
+            Step::Optional(steps, _) => match steps.parse_from(cursor) {
+                Ok(matched) => {
+                    return Ok(StepMatch {
+                        capture: Some(Capture::Optional(Some(matched.captures))),
+                        skipped: matched.skipped,
+                        context: matched.context,
+                    })
+                }
+                Err(failure) if failure.position == start => {
+                    cursor.rewind(start);
+                    return Ok(StepMatch {
+                        capture: Some(Capture::Optional(None)),
+                        skipped: Some(failure),
+                        context: None,
+                    });
+                }
+                Err(failure) => return Err(failure),
+            },
+            Step::Many(steps, _) => {
+                let mut repetitions: Vec<Captures> = Vec::new();
+                let mut context: Option<String> = None;
+
+                loop {
+                    let repetition_start = cursor.position();
 
-                    if found_delimiters {
-                        map.insert(step_name.clone(), Token::List(token_stream));
+                    match steps.parse_from(cursor) {
+                        // A grammar that matches without consuming anything would match forever
+                        Ok(matched) if cursor.position() == repetition_start => {
+                            repetitions.push(matched.captures);
+                            return Ok(StepMatch {
+                                capture: Some(Capture::Repeated(repetitions)),
+                                skipped: matched.skipped,
+                                context,
+                            });
+                        }
+                        Ok(matched) => {
+                            repetitions.push(matched.captures);
+                            context = matched.context;
+                        }
+                        Err(failure) if failure.position == repetition_start => {
+                            cursor.rewind(repetition_start);
+                            return Ok(StepMatch {
+                                capture: Some(Capture::Repeated(repetitions)),
+                                skipped: Some(ParseFailure {
+                                    context: context.clone(),
+                                    ..failure
+                                }),
+                                context,
+                            });
+                        }
+                        Err(failure) => return Err(failure),
                     }
                 }
             }
+            Step::OneOf(alternatives, _) => {
+                let mut best_failure: Option<ParseFailure> = None;
+
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    match alternative.parse_from(cursor) {
+                        Ok(matched) => {
+                            return Ok(StepMatch {
+                                capture: Some(Capture::Choice(idx, matched.captures)),
+                                skipped: matched.skipped,
+                                context: matched.context,
+                            })
+                        }
+                        Err(failure) => {
+                            cursor.rewind(start);
+                            best_failure = Some(match best_failure {
+                                Some(best) => best.merge(failure),
+                                None => failure,
+                            });
+                        }
+                    }
+                }
+
+                return Err(best_failure.unwrap_or_else(|| cursor.failure(Vec::new())));
+            }
+            Step::Repeated(steps, separator, _) => {
+                let mut matched = steps.parse_from(cursor)?;
+                let mut repetitions = vec![matched.captures];
+
+                loop {
+                    let separator_position = cursor.position();
+
+                    if !cursor.eat(separator) {
+                        let missing_separator = ParseFailure {
+                            context: matched.context.clone(),
+                            ..cursor.failure(vec![separator.describe()])
+                        };
+
+                        return Ok(StepMatch {
+                            capture: Some(Capture::Repeated(repetitions)),
+                            skipped: Some(match matched.skipped {
+                                Some(skipped) => skipped.merge(missing_separator),
+                                None => missing_separator,
+                            }),
+                            context: matched.context,
+                        });
+                    }
+
+                    // A repetition that fails straight away is reported as following the separator
+                    matched = steps.parse_from(cursor).map_err(|failure| {
+                        if failure.position == separator_position + 1 {
+                            ParseFailure {
+                                context: Some(separator.describe()),
+                                ..failure
+                            }
+                        } else {
+                            failure
+                        }
+                    })?;
+                    repetitions.push(matched.captures);
+                }
+            }
+        };
+
+        Ok(StepMatch::new(Some(capture)))
+    }
+}
+
+/// EBNF rules written so far, as pairs of rule name and definition
+type Rules = Vec<(String, String)>;
+
+impl ParseSteps {
+    /// Describes the grammar as EBNF, one rule per line. The first rule, named after `statement`, matches the whole
+    /// grammar. Nested grammars that were given a description and contain several items get a rule of their own.
+    pub(crate) fn to_ebnf(&self, statement: &str) -> String {
+        let mut rules: Rules = Vec::new();
+        let definition = self.write_ebnf(&mut rules);
+        rules.insert(0, (rule_name(statement), definition));
+
+        rules
+            .iter()
+            .map(|(name, definition)| format!("{} = {} ;", name, definition))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn write_ebnf(&self, rules: &mut Rules) -> String {
+        self.steps
+            .iter()
+            .map(|step| step.write_ebnf(rules))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl DescribedStep {
+    fn write_ebnf(&self, rules: &mut Rules) -> String {
+        match &self.step {
+            Step::Token(token) | Step::NamedToken(token, _) => match (token.text(), &self.description) {
+                (Some(text), _) => terminal(text),
+                (None, Some(description)) => rule_name(description),
+                (None, None) => rule_name(&token.describe()),
+            },
+            Step::Keyword(keyword) => terminal(&keyword.to_uppercase()),
+            Step::NamedStream(start_token, end_token, _) => {
+                format!("{} {{ token }} {}", token_ebnf(start_token), token_ebnf(end_token))
+            }
+            Step::Expression(_) => rule_name(self.description.as_deref().unwrap_or("expression")),
+            Step::Parser(_, name) => rule_name(self.description.as_deref().unwrap_or(name)),
+            Step::Optional(steps, _) => format!("[ {} ]", self.write_nested(&[steps], rules).0),
+            Step::Many(steps, _) => format!("{{ {} }}", self.write_nested(&[steps], rules).0),
+            Step::OneOf(alternatives, _) => {
+                let alternatives: Vec<&ParseSteps> = alternatives.iter().collect();
+                match self.write_nested(&alternatives, rules) {
+                    (text, true) => text,
+                    (text, false) => format!("( {} )", text),
+                }
+            }
+            Step::Repeated(steps, separator, _) => {
+                let item = match self.write_nested(&[steps], rules) {
+                    (text, true) => text,
+                    (text, false) => format!("( {} )", text),
+                };
+                format!("{} {{ {} {} }}", item, token_ebnf(separator), item)
+            }
+        }
+    }
+
+    /// Writes the grammars nested in this step as alternatives, returning them along with whether they form a
+    /// single item. If this step was described, several items are moved into a rule named after the description.
+    fn write_nested(&self, grammars: &[&ParseSteps], rules: &mut Rules) -> (String, bool) {
+        let rule_position = rules.len();
+        let text = grammars
+            .iter()
+            .map(|grammar| grammar.write_ebnf(rules))
+            .collect::<Vec<String>>()
+            .join(" | ");
+        let single = matches!(grammars, [grammar] if grammar.steps.len() == 1);
+
+        match &self.description {
+            Some(description) if !single => {
+                let name = rule_name(description);
+                if !rules.iter().any(|(existing, _)| *existing == name) {
+                    rules.insert(rule_position, (name.clone(), text));
+                }
+                (name, true)
+            }
+            _ => (text, single),
+        }
+    }
+}
+
+fn terminal(text: &str) -> String {
+    format!("\"{}\"", text)
+}
+
+fn token_ebnf(token: &Token) -> String {
+    match token.text() {
+        Some(text) => terminal(text),
+        None => rule_name(&token.describe()),
+    }
+}
+
+/// Turns a name such as "CREATE TABLE" or "column type" into an EBNF rule name, e.g. `column_type`
+fn rule_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{BinaryOperator, Expr},
+        errors::DbErr,
+        queries::{
+            parse_steps::{Capture, Captures, ParseFailure, ParseSteps},
+            token_cursor::TokenCursor,
+        },
+        tokenizer::{Token, Tokenizer},
+    };
+
+    fn failure(position: usize, expected: &[&str], context: Option<&str>) -> ParseFailure {
+        ParseFailure {
+            position,
+            expected: expected.iter().map(|description| description.to_string()).collect(),
+            context: context.map(String::from),
+            unclosed: None,
+        }
+    }
+
+    #[test]
+    fn failure_describes_previous_step() {
+        let steps = ParseSteps::new()
+            .add_step(Token::Delete)
+            .add_step(Token::Table)
+            .add_named_step(Token::Identifier("_".into()), "Name")
+            .described("table name")
+            .add_step(Token::SemiColon);
+        let tokenizer = Tokenizer::new();
+
+        let tokens = tokenizer.tokenize_spanned("DELETE users").unwrap();
+        assert_eq!(failure(1, &["`TABLE`"], Some("`DELETE`")), steps.parse(&tokens).unwrap_err());
+
+        let tokens = tokenizer.tokenize_spanned("DELETE TABLE users (").unwrap();
+        assert_eq!(failure(3, &["`;`"], Some("table name")), steps.parse(&tokens).unwrap_err());
+
+        let tokens = tokenizer.tokenize_spanned("TABLE").unwrap();
+        assert_eq!(failure(0, &["`DELETE`"], None), steps.parse(&tokens).unwrap_err());
+    }
+
+    #[test]
+    fn typed_captures() {
+        let steps = ParseSteps::new()
+            .add_named_step(Token::Identifier("_".into()), "Name")
+            .add_step(Token::Equals)
+            .add_expression("Value");
+        let tokens = Tokenizer::new().tokenize_spanned("total = price * 2").unwrap();
+        let mut captures = steps.parse(&tokens).unwrap();
+
+        assert_eq!(Ok(String::from("total")), captures.take_identifier("Name"));
+        assert_eq!(
+            Ok(Expr::binary(Expr::column("price"), BinaryOperator::Multiply, Expr::integer(2))),
+            captures.take_expression("Value")
+        );
+        assert!(captures.take("Value").is_err());
+    }
+
+    fn parse_star(cursor: &mut TokenCursor) -> Result<Capture, ParseFailure> {
+        cursor.expect(&Token::Asterisk)?;
+        cursor.expect(&Token::Asterisk)?;
+        Ok(Capture::Tokens(vec![Token::Asterisk, Token::Asterisk]))
+    }
+
+    #[test]
+    fn parser_step_failures() {
+        let steps = ParseSteps::new()
+            .add_step(Token::Delete)
+            .add_parser(parse_star, "Stars")
+            .described("stars")
+            .add_expression("Value");
+        let tokenizer = Tokenizer::new();
+
+        let tokens = tokenizer.tokenize_spanned("DELETE * *").unwrap();
+        assert_eq!(failure(3, &["expression"], Some("stars")), steps.parse(&tokens).unwrap_err());
+
+        // Failing part way through a sub-parser keeps the sub-parser's own context
+        let tokens = tokenizer.tokenize_spanned("DELETE * 1").unwrap();
+        assert_eq!(failure(2, &["`*`"], Some("`*`")), steps.parse(&tokens).unwrap_err());
+
+        let tokens = tokenizer.tokenize_spanned("DELETE 1").unwrap();
+        assert_eq!(failure(1, &["`*`"], Some("`DELETE`")), steps.parse(&tokens).unwrap_err());
+    }
+
+    fn identifier() -> Token {
+        Token::Identifier("_".into())
+    }
+
+    fn parse(steps: &ParseSteps, query: &str) -> Result<Captures, ParseFailure> {
+        steps.parse(&Tokenizer::new().tokenize_spanned(query).unwrap())
+    }
+
+    #[test]
+    fn optional_steps() {
+        let steps = ParseSteps::new()
+            .add_step(Token::Drop)
+            .add_optional(ParseSteps::new().add_step(Token::If).add_step(Token::Exists), "IfExists")
+            .add_named_step(identifier(), "Name");
+
+        let mut captures = parse(&steps, "DROP IF EXISTS users").unwrap();
+        assert_eq!(Ok(Some(Captures::default())), captures.take_optional("IfExists"));
+
+        let mut captures = parse(&steps, "DROP users").unwrap();
+        assert_eq!(Ok(None), captures.take_optional("IfExists"));
+        assert_eq!(Ok(String::from("users")), captures.take_identifier("Name"));
+
+        // A partial match is an error rather than a skipped step
+        assert_eq!(failure(2, &["`EXISTS`"], Some("`IF`")), parse(&steps, "DROP IF users").unwrap_err());
+        // A skipped step is included in what was expected
+        assert_eq!(
+            failure(1, &["`IF`", "identifier"], Some("`DROP`")),
+            parse(&steps, "DROP 1").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn one_of_steps() {
+        let steps = ParseSteps::new()
+            .add_one_of(
+                vec![
+                    ParseSteps::new().add_step(Token::Delete),
+                    ParseSteps::new().add_step(Token::Drop).add_named_step(identifier(), "Name"),
+                ],
+                "Keyword",
+            )
+            .add_step(Token::Table);
+
+        assert_eq!(
+            Ok((0, Captures::default())),
+            parse(&steps, "DELETE TABLE").unwrap().take_choice("Keyword")
+        );
+
+        let (idx, mut captures) = parse(&steps, "DROP users TABLE").unwrap().take_choice("Keyword").unwrap();
+        assert_eq!(1, idx);
+        assert_eq!(Ok(String::from("users")), captures.take_identifier("Name"));
+
+        assert_eq!(failure(0, &["`DELETE`", "`DROP`"], None), parse(&steps, "TABLE").unwrap_err());
+        assert_eq!(
+            failure(1, &["identifier"], Some("`DROP`")),
+            parse(&steps, "DROP TABLE").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn repeated_steps() {
+        let steps = ParseSteps::new()
+            .add_step(Token::OpenBracket)
+            .add_repeated(
+                ParseSteps::new()
+                    .add_named_step(identifier(), "Name")
+                    .add_optional(ParseSteps::new().add_named_step(Token::Integer(0), "Size"), "Size"),
+                Token::Comma,
+                "Items",
+            )
+            .add_step(Token::CloseBracket);
+
+        let items = parse(&steps, "(a, b 2, c)").unwrap().take_repeated("Items").unwrap();
+        let names: Vec<String> = items.into_iter().map(|mut item| item.take_identifier("Name").unwrap()).collect();
+        assert_eq!(vec!["a", "b", "c"], names);
+
+        assert_eq!(failure(1, &["identifier"], Some("`(`")), parse(&steps, "()").unwrap_err());
+        assert_eq!(failure(3, &["identifier"], Some("`,`")), parse(&steps, "(a, )").unwrap_err());
+        // Everything that could have continued the list is expected
+        assert_eq!(
+            failure(2, &["integer", "`,`", "`)`"], Some("identifier")),
+            parse(&steps, "(a b)").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn many_steps() {
+        let steps = ParseSteps::new()
+            .add_named_step(identifier(), "Name")
+            .add_many(ParseSteps::new().add_step(Token::Not).add_step(Token::Null), "Constraints")
+            .add_step(Token::SemiColon);
+
+        assert_eq!(Ok(Vec::new()), parse(&steps, "a;").unwrap().take_repeated("Constraints"));
+        assert_eq!(
+            Ok(vec![Captures::default(), Captures::default()]),
+            parse(&steps, "a NOT NULL NOT NULL;").unwrap().take_repeated("Constraints")
+        );
+
+        assert_eq!(failure(2, &["`NULL`"], Some("`NOT`")), parse(&steps, "a NOT;").unwrap_err());
+        assert_eq!(failure(1, &["`NOT`", "`;`"], Some("identifier")), parse(&steps, "a b").unwrap_err());
+        assert_eq!(
+            failure(3, &["`NOT`", "`;`"], Some("`NULL`")),
+            parse(&steps, "a NOT NULL b").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn stream_without_end_token() {
+        let steps = ParseSteps::new().add_token_capture_stream(Token::OpenBracket, Token::CloseBracket, String::from("Stream"));
+
+        assert_eq!(
+            Ok(Capture::Tokens(vec![Token::Integer(1), Token::Comma])),
+            parse(&steps, "(1,)").unwrap().take("Stream")
+        );
+        assert_eq!(
+            ParseFailure {
+                unclosed: Some(0),
+                ..failure(3, &["`)`"], Some("integer"))
+            },
+            parse(&steps, "(1 2").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn stream_with_nested_brackets() {
+        let steps = ParseSteps::new()
+            .add_token_capture_stream(Token::OpenBracket, Token::CloseBracket, String::from("Stream"))
+            .add_step(Token::SemiColon);
+        let tokenizer = Tokenizer::new();
+
+        let expected = tokenizer.tokenize("a DECIMAL(10, (2)), b").unwrap();
+        assert_eq!(
+            Ok(Capture::Tokens(expected)),
+            parse(&steps, "(a DECIMAL(10, (2)), b);").unwrap().take("Stream")
+        );
+
+        // The failure happens at the end, but the bracket that was never closed is to blame
+        let query = "(a (b (c);";
+        let tokens = tokenizer.tokenize_spanned(query).unwrap();
+        let unclosed = steps.parse(&tokens).unwrap_err();
+        assert_eq!(
+            ParseFailure {
+                unclosed: Some(2),
+                ..failure(8, &["`)`"], Some("`;`"))
+            },
+            unclosed
+        );
+        match unclosed.into_error(query, &tokens) {
+            DbErr::Syntax(err) => assert_eq!("unmatched `(` at 1:4", err.to_string()),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+        assert_eq!(Some(0), parse(&steps, "(a (b) (c);").unwrap_err().unclosed);
+    }
+
+    #[test]
+    fn leftover_tokens() {
+        let steps = ParseSteps::new()
+            .add_step(Token::Drop)
+            .add_named_step(identifier(), "Name")
+            .described("table name");
+
+        assert!(parse(&steps, "DROP users").is_ok());
+        assert_eq!(
+            failure(2, &["end of statement"], Some("table name")),
+            parse(&steps, "DROP users garbage garbage").unwrap_err()
+        );
+
+        let steps = steps.add_optional(ParseSteps::new().add_step(Token::SemiColon), "End");
+        assert_eq!(
+            failure(2, &["`;`", "end of statement"], Some("table name")),
+            parse(&steps, "DROP users garbage").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn merge_keeps_furthest_failure() {
+        let near = failure(1, &["`TABLE`"], None);
+        let far = failure(3, &["`(`"], Some("table name"));
+
+        assert_eq!(far, near.clone().merge(far.clone()));
+        assert_eq!(far, far.clone().merge(near));
+    }
+
+    #[test]
+    fn merge_combines_expected_tokens() {
+        let first = failure(2, &["`,`", "`)`"], Some("column type"));
+        let second = failure(2, &["`)`", "`(`"], Some("column type"));
+        let other_context = failure(2, &["`;`"], Some("table name"));
+
+        assert_eq!(failure(2, &["`,`", "`)`", "`(`"], Some("column type")), first.clone().merge(second));
+        assert_eq!(failure(2, &["`,`", "`)`", "`;`"], None), first.merge(other_context));
+    }
+
+    #[test]
+    fn error_messages() {
+        let query = "CREATE TABLE t (a int garbage)";
+        let tokens = Tokenizer::new().tokenize_spanned(query).unwrap();
+
+        let err = failure(6, &["`,`", "`)`"], Some("column type")).into_error(query, &tokens);
+        match err {
+            DbErr::Syntax(err) => assert_eq!("expected one of `,` or `)` after column type at 1:23", err.to_string()),
+            err => panic!("Expected a syntax error, got {:?}", err),
         }
 
-        Ok(map)
+        let err = failure(8, &["`;`"], Some("`)`")).into_error(query, &tokens);
+        match err {
+            DbErr::Syntax(err) => {
+                assert_eq!("expected `;` after `)`, but the statement ended at 1:31", err.to_string())
+            }
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
     }
 }
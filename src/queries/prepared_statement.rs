@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Expr, Literal},
+    database::{ColumnDescription, Database},
+    errors::{DbErr, SyntaxError},
+    field_value::FieldValue,
+    tokenizer::{Placeholder, Token},
+};
+
+use super::{query_parser, statement_registry::StatementRegistry, Query, QuerySuccess};
+
+/// The highest number of arguments a query can take, so `$1000000000` can't make binding allocate gigabytes
+const MAX_PARAMETERS: usize = 65535;
+
+/// A query that has been parsed and planned once, and can be executed any number of times with different arguments.
+///
+/// Arguments are bound by position. `?` placeholders are numbered in the order they appear, `$n` placeholders
+/// use their number, and each distinct `:name` is numbered by its first appearance. A query can only use
+/// one of these styles.
+pub struct PreparedStatement {
+    plan: Box<dyn Query>,
+    parameter_names: Vec<String>,
+    parameter_count: usize,
+}
+
+impl PreparedStatement {
+    pub(crate) fn new(registry: &StatementRegistry, query: &str) -> Result<PreparedStatement, DbErr> {
+        let mut tokens = registry.tokenizer().tokenize_spanned(query)?;
+
+        let mut parameter_names: Vec<String> = Vec::new();
+        let mut parameter_count = 0;
+        let mut style: Option<Placeholder> = None;
+
+        for spanned in tokens.iter_mut() {
+            let Token::Placeholder(placeholder) = &spanned.token else {
+                continue;
+            };
+
+            if style
+                .as_ref()
+                .is_some_and(|style| std::mem::discriminant(style) != std::mem::discriminant(placeholder))
+            {
+                return Err(DbErr::Syntax(SyntaxError::new(
+                    String::from("Can't mix placeholder styles in one query"),
+                    spanned.span,
+                )));
+            }
+            style = Some(placeholder.clone());
+
+            let argument_idx = match placeholder {
+                Placeholder::Anonymous => parameter_count,
+                Placeholder::Numbered(number) => number - 1,
+                Placeholder::Named(name) => match parameter_names.iter().position(|existing| existing == name) {
+                    Some(position) => position,
+                    None => {
+                        parameter_names.push(name.clone());
+                        parameter_names.len() - 1
+                    }
+                },
+            };
+            if argument_idx >= MAX_PARAMETERS {
+                return Err(DbErr::Syntax(SyntaxError::new(
+                    format!("A query can't have more than {} parameters", MAX_PARAMETERS),
+                    spanned.span,
+                )));
+            }
+
+            parameter_count = parameter_count.max(argument_idx + 1);
+            // The parser only needs to know which argument each placeholder takes
+            spanned.token = Token::Placeholder(Placeholder::Numbered(argument_idx + 1));
+        }
+
+        Ok(PreparedStatement {
+            plan: query_parser::plan_tokens(registry, query, &tokens)?,
+            parameter_names,
+            parameter_count,
+        })
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_count
+    }
+
+    /// The argument position of a `:name` placeholder
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameter_names.iter().position(|existing| existing == name)
+    }
+
+    /// The planned query with every placeholder replaced by its argument
+    fn bind(&self, database: &Database, arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        if arguments.len() != self.parameter_count {
+            return Err(DbErr::ParameterCountMismatch {
+                expected: self.parameter_count,
+                provided: arguments.len(),
+            });
+        }
+
+        self.plan.bind(database, arguments)
+    }
+}
+
+/// Binds an expression whose value is stored in `column`. An argument that makes up the whole expression is
+/// converted to the column's type straight away, so one that doesn't fit is rejected before the query runs.
+pub(crate) fn bind_to_column(expr: &Expr, column: &ColumnDescription, arguments: &[FieldValue]) -> Result<Expr, DbErr> {
+    match expr {
+        Expr::Parameter(idx) if *idx < arguments.len() => {
+            let value = column.column_type.coerce(arguments[*idx].clone(), &column.column_name)?;
+            Ok(Expr::Literal(Literal::from(&value)))
+        }
+        expr => Ok(expr.bind(arguments)),
+    }
+}
+
+impl Database {
+    pub fn prepare(&self, query: &str) -> Result<PreparedStatement, DbErr> {
+        PreparedStatement::new(&self.registry(), query)
+    }
+
+    pub fn execute_prepared(&mut self, statement: &PreparedStatement, arguments: &[FieldValue]) -> Result<QuerySuccess, DbErr> {
+        // Without parameters, the plan made when the statement was prepared runs as it is
+        if statement.parameter_count == 0 && arguments.is_empty() {
+            return statement.plan.execute(self);
+        }
+
+        let query_plan = statement.bind(self, arguments)?;
+        query_parser::run_query_plan(self, query_plan)
+    }
+
+    /// Executes a prepared statement that uses `:name` placeholders, with the arguments given by name
+    pub fn execute_prepared_named(
+        &mut self,
+        statement: &PreparedStatement,
+        arguments: &HashMap<&str, FieldValue>,
+    ) -> Result<QuerySuccess, DbErr> {
+        if statement.parameter_names.len() != statement.parameter_count {
+            return Err(DbErr::Generic(String::from(
+                "Query has positional parameters, which can't be given by name",
+            )));
+        }
+
+        let mut positional: Vec<Option<FieldValue>> = (0..statement.parameter_count).map(|_| None).collect();
+
+        for (name, value) in arguments {
+            match statement.parameter_index(name) {
+                Some(idx) => positional[idx] = Some(value.clone()),
+                None => return Err(DbErr::Generic(format!("Query has no parameter named :{}", name))),
+            }
+        }
+
+        let positional = positional
+            .into_iter()
+            .zip(&statement.parameter_names)
+            .map(|(value, name)| value.ok_or_else(|| DbErr::Generic(format!("Missing argument for :{}", name))))
+            .collect::<Result<Vec<FieldValue>, DbErr>>()?;
+        self.execute_prepared(statement, &positional)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::{query_parser, result_set::ResultSet, QuerySuccess},
+    };
+
+    fn database() -> Database {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score DOUBLE, active BOOLEAN, avatar BLOB);",
+        )
+        .unwrap();
+        database
+    }
+
+    fn rows(database: &mut Database, query: &str) -> Vec<Vec<FieldValue>> {
+        match query_parser::execute_query(database, query) {
+            Ok(QuerySuccess::Rows(ResultSet { rows, .. })) => rows,
+            result => panic!("Expected rows, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn count_parameters() {
+        let database = database();
+
+        assert_eq!(0, database.prepare("DELETE TABLE users;").unwrap().parameter_count());
+        assert_eq!(3, database.prepare("SELECT ?, ?, ?;").unwrap().parameter_count());
+        assert_eq!(2, database.prepare("SELECT $2, $1, $2;").unwrap().parameter_count());
+        assert_eq!(2, database.prepare("SELECT :a, :b, :a;").unwrap().parameter_count());
+    }
+
+    #[test]
+    fn bind_arguments() {
+        let mut database = database();
+
+        let insert = database.prepare("INSERT INTO users VALUES (?, ?, ?, ?, ?);").unwrap();
+        let arguments = [
+            FieldValue::Integer(1),
+            FieldValue::Text(String::from("it's")),
+            FieldValue::Integer(2),
+            FieldValue::Boolean(false),
+            FieldValue::Blob(vec![1, 2]),
+        ];
+        assert_eq!(Ok(QuerySuccess::RowsAffected(1)), database.execute_prepared(&insert, &arguments));
+        assert_eq!(
+            vec![vec![
+                FieldValue::Integer(1),
+                FieldValue::Text(String::from("it's")),
+                FieldValue::Double(2.0),
+                FieldValue::Boolean(false),
+                FieldValue::Blob(vec![1, 2]),
+            ]],
+            rows(&mut database, "SELECT * FROM users;")
+        );
+
+        let numbered = database.prepare("SELECT $2, $1, $2 - 1;").unwrap();
+        match database.execute_prepared(&numbered, &[FieldValue::Null, FieldValue::Integer(-7)]) {
+            Ok(QuerySuccess::Rows(result_set)) => assert_eq!(
+                vec![vec![FieldValue::Integer(-7), FieldValue::Null, FieldValue::Integer(-8)]],
+                result_set.rows
+            ),
+            result => panic!("Expected rows, got {:?}", result),
+        }
+
+        let named = database.prepare("SELECT :b, :a;").unwrap();
+        assert_eq!(Some(1), named.parameter_index("a"));
+        assert_eq!(None, named.parameter_index("c"));
+    }
+
+    #[test]
+    fn arguments_are_checked_against_their_columns() {
+        let mut database = database();
+        let insert = database.prepare("INSERT INTO users (id, name) VALUES (?, ?);").unwrap();
+        let update = database.prepare("UPDATE users SET score = ? WHERE id = ?;").unwrap();
+
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't store TEXT in INTEGER column id"))),
+            database.execute_prepared(&insert, &[FieldValue::Text(String::from("1")), FieldValue::Null])
+        );
+        assert_eq!(
+            Err(DbErr::ArithmeticError(String::from(
+                "4294967296 is out of range for INTEGER column id"
+            ))),
+            database.execute_prepared(&insert, &[FieldValue::Integer(1 << 32), FieldValue::Null])
+        );
+        assert_eq!(
+            Err(DbErr::TypeMismatch(String::from("Can't store BOOLEAN in DOUBLE column score"))),
+            database.execute_prepared(&update, &[FieldValue::Boolean(true), FieldValue::Integer(1)])
+        );
+        assert!(rows(&mut database, "SELECT * FROM users;").is_empty());
+    }
+
+    #[test]
+    fn unbound_parameters() {
+        let mut database = database();
+
+        assert_eq!(
+            Err(DbErr::Generic(String::from("Statement doesn't take parameters"))),
+            database
+                .prepare("CREATE TABLE t (a INTEGER DEFAULT ?);")
+                .and_then(|statement| database.execute_prepared(&statement, &[FieldValue::Integer(1)]))
+        );
+        assert_eq!(
+            Err(DbErr::Generic(String::from(
+                "Parameter $1 has no value, it can only be given to a prepared statement"
+            ))),
+            query_parser::execute_query(&mut database, "SELECT $1;")
+        );
+    }
+
+    #[test]
+    fn argument_count_mismatch() {
+        let mut database = database();
+        let statement = database.prepare("DELETE TABLE users;").unwrap();
+
+        assert_eq!(
+            DbErr::ParameterCountMismatch { expected: 0, provided: 1 },
+            database.execute_prepared(&statement, &[FieldValue::Integer(1)]).unwrap_err()
+        );
+
+        let statement = database.prepare("SELECT ?, ?;").unwrap();
+        assert_eq!(
+            DbErr::ParameterCountMismatch { expected: 2, provided: 1 },
+            database.execute_prepared(&statement, &[FieldValue::Integer(1)]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn mixed_placeholder_styles() {
+        let database = database();
+
+        match database.prepare("SELECT ?, $1;") {
+            Err(DbErr::Syntax(err)) => assert_eq!("Can't mix placeholder styles in one query at 1:11", err.to_string()),
+            _ => panic!("Expected a syntax error"),
+        }
+        assert!(database.prepare("SELECT :a, ?;").is_err());
+    }
+
+    #[test]
+    fn too_many_parameters() {
+        let mut database = database();
+
+        assert_eq!(65535, database.prepare("SELECT $65535;").unwrap().parameter_count());
+        for query in ["SELECT $65536;", "SELECT $18446744073709551615;"] {
+            match database.prepare(query) {
+                Err(DbErr::Syntax(err)) => {
+                    assert_eq!("A query can't have more than 65535 parameters at 1:8", err.to_string())
+                }
+                result => panic!(
+                    "Expected a syntax error, got {:?}",
+                    result.map(|statement| statement.parameter_count())
+                ),
+            }
+        }
+
+        // Numbered placeholders can't be bound by name
+        let statement = database.prepare("SELECT $1000;").unwrap();
+        assert_eq!(
+            DbErr::Generic(String::from("Query has positional parameters, which can't be given by name")),
+            database.execute_prepared_named(&statement, &HashMap::new()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn named_arguments() {
+        let mut database = database();
+        let insert = database
+            .prepare("INSERT INTO users (id, name, score) VALUES (:id, :name, :id * 2);")
+            .unwrap();
+
+        let arguments = HashMap::from([("id", FieldValue::Integer(3)), ("name", FieldValue::Text(String::from("ann")))]);
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(1)),
+            database.execute_prepared_named(&insert, &arguments)
+        );
+        assert_eq!(
+            vec![vec![FieldValue::Text(String::from("ann")), FieldValue::Double(6.0)]],
+            rows(&mut database, "SELECT name, score FROM users;")
+        );
+
+        let arguments = HashMap::from([("other", FieldValue::Integer(1))]);
+        assert_eq!(
+            DbErr::Generic(String::from("Query has no parameter named :other")),
+            database.execute_prepared_named(&insert, &arguments).unwrap_err()
+        );
+        // Every argument after the missing one still goes to its own parameter
+        let arguments = HashMap::from([("name", FieldValue::Text(String::from("bob")))]);
+        assert_eq!(
+            DbErr::Generic(String::from("Missing argument for :id")),
+            database.execute_prepared_named(&insert, &arguments).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn execute_many_times() {
+        let mut database = Database::new();
+        let create = database.prepare("CREATE TABLE users (name string);").unwrap();
+        let delete = database.prepare("DELETE TABLE users;").unwrap();
+
+        for _ in 0..3 {
+            assert!(database.execute_prepared(&create, &[]).is_ok());
+            assert!(database.get_table("users").is_some());
+            assert!(database.execute_prepared(&delete, &[]).is_ok());
+            assert!(database.get_table("users").is_none());
+        }
+
+        assert_eq!(DbErr::TableNotExists, database.execute_prepared(&delete, &[]).unwrap_err());
+
+        let mut database = self::database();
+        let insert = database.prepare("INSERT INTO users (id) VALUES (?);").unwrap();
+        let select = database.prepare("SELECT id FROM users WHERE id > ? ORDER BY id;").unwrap();
+        for id in 1..=4 {
+            database.execute_prepared(&insert, &[FieldValue::Integer(id)]).unwrap();
+        }
+        match database.execute_prepared(&select, &[FieldValue::Integer(2)]) {
+            Ok(QuerySuccess::Rows(result_set)) => {
+                assert_eq!(vec![vec![FieldValue::Integer(3)], vec![FieldValue::Integer(4)]], result_set.rows)
+            }
+            result => panic!("Expected rows, got {:?}", result),
+        }
+    }
+}
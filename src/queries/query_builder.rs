@@ -1,36 +1,290 @@
-use std::collections::HashMap;
+use crate::{errors::DbErr, tokenizer::Token};
 
-use crate::tokenizer::Token;
+use super::{
+    create_table_query::CreateTableQuery,
+    delete_query::DeleteQuery,
+    delete_table_query::DeleteTableQuery,
+    insert_query::InsertQuery,
+    parse_steps::{Captures, ParseSteps},
+    select_query::SelectQuery,
+    update_query::UpdateQuery,
+    Query,
+};
 
-use super::{create_table_query::CreateTableQuery, delete_table_query::DeleteTableQuery, parse_steps::ParseSteps, Query};
-
-type QueryFactory = Box<dyn Fn(HashMap<String, Token>) -> Box<dyn Query>>;
+type QueryFactory = Box<dyn Fn(Captures) -> Result<Box<dyn Query>, DbErr>>;
 
 pub struct QueryBuilder {
+    /// Names the statement in error messages, e.g. `CREATE TABLE`
+    pub name: String,
     pub steps: ParseSteps,
     pub factory: QueryFactory,
 }
 
 pub fn get_builders() -> Vec<QueryBuilder> {
-    let mut query_factories: Vec<QueryBuilder> = Vec::new();
+    vec![
+        QueryBuilder {
+            name: String::from("CREATE TABLE"),
+            steps: ParseSteps::new()
+                .add_step(Token::Create)
+                .add_step(Token::Table)
+                .add_optional(
+                    ParseSteps::new().add_step(Token::If).add_step(Token::Not).add_step(Token::Exists),
+                    "IfNotExists",
+                )
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_step(Token::OpenBracket)
+                .add_repeated(
+                    ParseSteps::new()
+                        .add_one_of(vec![column_definition(), table_constraint()], "Element")
+                        .described("table element"),
+                    Token::Comma,
+                    "Elements",
+                )
+                .add_step(Token::CloseBracket)
+                .described("column definitions")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(CreateTableQuery::new(captures)?))),
+        },
+        QueryBuilder {
+            name: String::from("DELETE TABLE"),
+            steps: ParseSteps::new()
+                .add_one_of(
+                    vec![ParseSteps::new().add_step(Token::Delete), ParseSteps::new().add_step(Token::Drop)],
+                    "Keyword",
+                )
+                .add_step(Token::Table)
+                .add_optional(ParseSteps::new().add_step(Token::If).add_step(Token::Exists), "IfExists")
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(DeleteTableQuery::new(captures)?))),
+        },
+        QueryBuilder {
+            name: String::from("INSERT"),
+            steps: ParseSteps::new()
+                .add_step(Token::Insert)
+                .add_step(Token::Into)
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_optional(
+                    ParseSteps::new()
+                        .add_step(Token::OpenBracket)
+                        .add_repeated(
+                            ParseSteps::new()
+                                .add_named_step(Token::Identifier("_".into()), "Name")
+                                .described("column name"),
+                            Token::Comma,
+                            "Columns",
+                        )
+                        .add_step(Token::CloseBracket),
+                    "Columns",
+                )
+                .described("column list")
+                .add_step(Token::Values)
+                .add_repeated(
+                    ParseSteps::new()
+                        .add_step(Token::OpenBracket)
+                        .add_repeated(ParseSteps::new().add_expression("Value"), Token::Comma, "Values")
+                        .add_step(Token::CloseBracket)
+                        .described("row"),
+                    Token::Comma,
+                    "Rows",
+                )
+                .described("row")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(InsertQuery::new(captures)?))),
+        },
+        QueryBuilder {
+            name: String::from("SELECT"),
+            steps: ParseSteps::new()
+                .add_step(Token::Select)
+                .add_repeated(
+                    ParseSteps::new().add_one_of(
+                        vec![
+                            ParseSteps::new().add_step(Token::Asterisk),
+                            ParseSteps::new().add_expression("Value").add_optional(
+                                ParseSteps::new()
+                                    .add_optional(ParseSteps::new().add_step(Token::As), "As")
+                                    .add_named_step(Token::Identifier("_".into()), "Alias"),
+                                "Alias",
+                            ),
+                        ],
+                        "Item",
+                    ),
+                    Token::Comma,
+                    "Items",
+                )
+                .add_optional(
+                    ParseSteps::new()
+                        .add_step(Token::From)
+                        .add_named_step(Token::Identifier("_".into()), "Name")
+                        .described("table name"),
+                    "From",
+                )
+                .add_optional(ParseSteps::new().add_step(Token::Where).add_expression("Condition"), "Where")
+                .add_optional(
+                    ParseSteps::new()
+                        .add_step(Token::Order)
+                        .add_step(Token::By)
+                        .add_repeated(order_by_item(), Token::Comma, "Keys"),
+                    "OrderBy",
+                )
+                .add_optional(
+                    ParseSteps::new().add_step(Token::Limit).add_named_step(Token::Integer(0), "Count"),
+                    "Limit",
+                )
+                .add_optional(
+                    ParseSteps::new()
+                        .add_step(Token::Offset)
+                        .add_named_step(Token::Integer(0), "Count")
+                        .add_optional(ParseSteps::new().add_one_of(row_or_rows(), "Rows"), "Rows"),
+                    "Offset",
+                )
+                .add_optional(
+                    ParseSteps::new()
+                        .add_step(Token::Fetch)
+                        .add_one_of(
+                            vec![ParseSteps::new().add_keyword("first"), ParseSteps::new().add_keyword("next")],
+                            "Position",
+                        )
+                        .add_optional(ParseSteps::new().add_named_step(Token::Integer(0), "Count"), "Count")
+                        .add_one_of(row_or_rows(), "Rows")
+                        .add_keyword("only"),
+                    "Fetch",
+                )
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(SelectQuery::new(captures)?))),
+        },
+        QueryBuilder {
+            name: String::from("UPDATE"),
+            steps: ParseSteps::new()
+                .add_step(Token::Update)
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_keyword("set")
+                .add_repeated(
+                    ParseSteps::new()
+                        .add_named_step(Token::Identifier("_".into()), "Column")
+                        .described("column name")
+                        .add_step(Token::Equals)
+                        .add_expression("Value"),
+                    Token::Comma,
+                    "Assignments",
+                )
+                .add_optional(ParseSteps::new().add_step(Token::Where).add_expression("Condition"), "Where")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(UpdateQuery::new(captures)?))),
+        },
+        QueryBuilder {
+            name: String::from("DELETE"),
+            steps: ParseSteps::new()
+                .add_step(Token::Delete)
+                .add_step(Token::From)
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_optional(ParseSteps::new().add_step(Token::Where).add_expression("Condition"), "Where")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(DeleteQuery::new(captures)?))),
+        },
+    ]
+}
+
+/// `name type [(parameters)] { column constraint }`
+fn column_definition() -> ParseSteps {
+    ParseSteps::new()
+        .add_named_step(Token::Identifier("_".into()), "Name")
+        .described("column name")
+        .add_named_step(Token::Identifier("_".into()), "Type")
+        .described("column type")
+        .add_optional(
+            ParseSteps::new()
+                .add_step(Token::OpenBracket)
+                .add_repeated(ParseSteps::new().add_named_step(Token::Integer(0), "Value"), Token::Comma, "Values")
+                .add_step(Token::CloseBracket)
+                .described("type parameters"),
+            "Parameters",
+        )
+        .described("type parameters")
+        .add_many(
+            ParseSteps::new().add_optional(constraint_name(), "ConstraintName").add_one_of(
+                vec![
+                    ParseSteps::new().add_step(Token::Not).add_step(Token::Null),
+                    ParseSteps::new().add_keyword("default").add_expression("Default"),
+                    ParseSteps::new().add_keyword("unique"),
+                    ParseSteps::new().add_step(Token::Primary).add_keyword("key"),
+                    check_constraint(),
+                ],
+                "Kind",
+            ),
+            "Constraints",
+        )
+        .described("column constraint")
+}
 
-    query_factories.push(QueryBuilder {
-        steps: ParseSteps::new()
-            .add_step(Token::Create)
-            .add_step(Token::Table)
+/// A constraint listed among the columns, which may cover several of them
+fn table_constraint() -> ParseSteps {
+    let column_names = || {
+        ParseSteps::new()
             .add_named_step(Token::Identifier("_".into()), "Name")
-            .add_token_capture_stream(Token::OpenBracket, Token::CloseBracket, String::from("TableDescription"))
-            .add_step(Token::SemiColon),
-        factory: Box::new(|data| Box::new(CreateTableQuery::new(data))),
-    });
-
-    query_factories.push(QueryBuilder {
-        steps: ParseSteps::new()
-            .add_step(Token::Delete)
-            .add_step(Token::Table)
-            .add_named_step(Token::Identifier("_".into()), "Name"),
-        factory: Box::new(|data| Box::new(DeleteTableQuery::new(data))),
-    });
-
-    query_factories
+            .described("column name")
+    };
+
+    ParseSteps::new().add_optional(constraint_name(), "ConstraintName").add_one_of(
+        vec![
+            ParseSteps::new()
+                .add_step(Token::Primary)
+                .add_keyword("key")
+                .add_step(Token::OpenBracket)
+                .add_repeated(column_names(), Token::Comma, "Columns")
+                .add_step(Token::CloseBracket),
+            ParseSteps::new()
+                .add_keyword("unique")
+                .add_step(Token::OpenBracket)
+                .add_repeated(column_names(), Token::Comma, "Columns")
+                .add_step(Token::CloseBracket),
+            check_constraint(),
+        ],
+        "Kind",
+    )
+}
+
+fn constraint_name() -> ParseSteps {
+    ParseSteps::new()
+        .add_step(Token::Constraint)
+        .add_named_step(Token::Identifier("_".into()), "Name")
+        .described("constraint name")
+}
+
+fn check_constraint() -> ParseSteps {
+    ParseSteps::new()
+        .add_keyword("check")
+        .add_step(Token::OpenBracket)
+        .add_expression("Check")
+        .add_step(Token::CloseBracket)
+}
+
+/// `expr [ASC | DESC] [NULLS FIRST | NULLS LAST]`
+fn order_by_item() -> ParseSteps {
+    ParseSteps::new()
+        .add_expression("Key")
+        .add_optional(
+            ParseSteps::new().add_one_of(
+                vec![ParseSteps::new().add_step(Token::Asc), ParseSteps::new().add_step(Token::Desc)],
+                "Direction",
+            ),
+            "Direction",
+        )
+        .add_optional(
+            ParseSteps::new().add_keyword("nulls").add_one_of(
+                vec![ParseSteps::new().add_keyword("first"), ParseSteps::new().add_keyword("last")],
+                "Position",
+            ),
+            "Nulls",
+        )
+}
+
+/// `ROW` and `ROWS` mean the same, and aren't reserved so that columns may still use the names
+fn row_or_rows() -> Vec<ParseSteps> {
+    vec![ParseSteps::new().add_keyword("row"), ParseSteps::new().add_keyword("rows")]
 }
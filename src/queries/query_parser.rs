@@ -1,59 +1,197 @@
-use crate::{database::Database, errors::DbErr, tokenizer::Tokenizer};
+use crate::{
+    ast::Statement,
+    database::Database,
+    errors::{DbErr, SyntaxError},
+    tokenizer::{Span, SpannedToken, Token, Tokens},
+};
 
-use super::{query_builder, Query};
+use super::{
+    parse_steps::{Captures, ParseFailure},
+    query_builder::QueryBuilder,
+    statement_registry::StatementRegistry,
+    Query, QuerySuccess,
+};
 
-pub fn execute_query(database: &mut Database, query: &str) -> Result<String, DbErr> {
-    let result = create_query_plan(database, query);
+/// The outcome of each statement in a script. Execution stops at the first statement that fails,
+/// so only the final result can be an error.
+pub struct ScriptResult {
+    pub results: Vec<Result<QuerySuccess, DbErr>>,
+    pub failed_statement: Option<usize>,
+}
 
-    match result {
-        Ok(query_plan) => {
-            for query in query_plan {
-                query.execute(database)?;
-            }
+/// Executes every statement in the query, returning the result of the last one or the first error
+pub fn execute_query(database: &mut Database, query: &str) -> Result<QuerySuccess, DbErr> {
+    execute_script(database, query).results.pop().unwrap_or(Ok(QuerySuccess::Done))
+}
+
+/// Executes a script of `;` separated statements in order, stopping at the first one that fails
+pub fn execute_script(database: &mut Database, query: &str) -> ScriptResult {
+    let registry = database.registry();
+    let mut tokens = registry.tokenizer().tokens(query);
+    let mut results: Vec<Result<QuerySuccess, DbErr>> = Vec::new();
+
+    loop {
+        let result = match next_statement(&mut tokens) {
+            Ok(Some(statement)) => plan_tokens(&registry, query, &statement).and_then(|query_plan| run_query_plan(database, query_plan)),
+            Ok(None) => break,
+            Err(err) => Err(err),
+        };
+
+        let failed = result.is_err();
+        results.push(result);
 
-            Ok("Done".into())
+        if failed {
+            break;
         }
-        Err(_) => Err(DbErr::Generic("Not done yet".into())),
     }
+
+    let failed_statement = match results.last() {
+        Some(Err(_)) => Some(results.len() - 1),
+        _ => None,
+    };
+
+    ScriptResult { results, failed_statement }
 }
 
-fn create_query_plan(_database: &mut Database, query: &str) -> Result<Vec<Box<dyn Query>>, DbErr> {
-    let tokenizer = Tokenizer::new();
+/// Parses every statement in the script without executing any of them
+pub(crate) fn parse_script(registry: &StatementRegistry, query: &str) -> Result<Vec<Statement>, DbErr> {
+    let mut tokens = registry.tokenizer().tokens(query);
+    let mut statements: Vec<Statement> = Vec::new();
+
+    while let Some(statement) = next_statement(&mut tokens)? {
+        match plan_tokens(registry, query, &statement)?.statement() {
+            Some(statement) => statements.push(statement),
+            None => return Err(DbErr::Generic(String::from("Statement has no syntax tree"))),
+        }
+    }
 
-    let query_builders = query_builder::get_builders();
-    let mut query_plan: Vec<Box<dyn Query>> = Vec::new();
+    Ok(statements)
+}
 
-    let parsed_tokens = tokenizer.tokenize(query).map_err(|_| DbErr::Generic(String::from("test")))?;
+/// Reads the tokens of the next statement, up to and including the `;` that ends it.
+/// A `;` inside brackets doesn't end the statement, and empty statements are skipped.
+fn next_statement(tokens: &mut Tokens) -> Result<Option<Vec<SpannedToken>>, DbErr> {
+    let mut statement: Vec<SpannedToken> = Vec::new();
+    let mut depth: usize = 0;
 
-    let mut best_progress: usize = 0;
-    let mut best_error: DbErr = DbErr::Generic(String::from("Unknown error"));
+    for spanned in tokens.by_ref() {
+        let spanned = spanned?;
 
-    for builder in query_builders {
-        match builder.steps.parse(&parsed_tokens) {
-            Ok(parsed) => {
-                let factory = builder.factory;
-                query_plan.push(factory(parsed));
+        match spanned.token {
+            Token::OpenBracket => depth += 1,
+            Token::CloseBracket => depth = depth.saturating_sub(1),
+            Token::SemiColon if depth == 0 && statement.is_empty() => continue,
+            Token::SemiColon if depth == 0 => {
+                statement.push(spanned);
+                return Ok(Some(statement));
             }
-            Err((error, progress)) => {
-                if progress > best_progress {
-                    best_error = error;
-                    best_progress = progress;
+            _ => {}
+        }
+
+        statement.push(spanned);
+    }
+
+    Ok(if statement.is_empty() { None } else { Some(statement) })
+}
+
+pub(crate) fn run_query_plan(database: &mut Database, query_plan: Box<dyn Query>) -> Result<QuerySuccess, DbErr> {
+    query_plan.execute(database)
+}
+
+#[cfg(test)]
+fn create_query_plan(database: &mut Database, query: &str) -> Result<Box<dyn Query>, DbErr> {
+    let registry = database.registry();
+
+    let parsed_tokens = registry.tokenizer().tokenize_spanned(query)?;
+
+    plan_tokens(&registry, query, &parsed_tokens)
+}
+
+/// Finds the one registered statement whose grammar matches all of the already tokenized input.
+/// `query` is only used to locate errors.
+pub(crate) fn plan_tokens(registry: &StatementRegistry, query: &str, parsed_tokens: &[SpannedToken]) -> Result<Box<dyn Query>, DbErr> {
+    let mut matched: Option<(&QueryBuilder, Captures)> = None;
+    let mut best_failure: Option<ParseFailure> = None;
+
+    for builder in registry.builders() {
+        match builder.steps.parse(parsed_tokens) {
+            Ok(captures) => {
+                if let Some((first, _)) = &matched {
+                    let span = parsed_tokens.first().map_or_else(|| Span::end_of(query), |token| token.span);
+                    return Err(DbErr::Syntax(SyntaxError::new(
+                        format!("ambiguous statement, it matches both {} and {}", first.name, builder.name),
+                        span,
+                    )));
                 }
+
+                matched = Some((builder, captures));
+            }
+            Err(failure) => {
+                best_failure = Some(match best_failure {
+                    Some(best) => best.merge(failure),
+                    None => failure,
+                });
             }
         }
     }
 
-    if query_plan.is_empty() {
-        return Err(best_error);
+    if let Some((builder, captures)) = matched {
+        return (builder.factory)(captures);
     }
 
-    Ok(query_plan)
+    let failure = best_failure.unwrap_or_else(|| ParseFailure {
+        position: 0,
+        expected: Vec::new(),
+        context: None,
+        unclosed: None,
+    });
+
+    match unmatched_bracket(parsed_tokens) {
+        // A missing bracket explains any failure that happened after it better than the failure itself
+        Some(bracket_idx) if bracket_idx <= failure.position => {
+            let bracket = &parsed_tokens[bracket_idx];
+            Err(DbErr::Syntax(SyntaxError::new(
+                format!("unmatched {}", bracket.token.describe()),
+                bracket.span,
+            )))
+        }
+        _ => Err(failure.into_error(query, parsed_tokens)),
+    }
+}
+
+/// Finds the first `)` without an opening bracket, or failing that the last `(` that is never closed
+fn unmatched_bracket(tokens: &[SpannedToken]) -> Option<usize> {
+    let mut open_brackets: Vec<usize> = Vec::new();
+
+    for (idx, spanned) in tokens.iter().enumerate() {
+        match spanned.token {
+            Token::OpenBracket => open_brackets.push(idx),
+            Token::CloseBracket if open_brackets.pop().is_none() => return Some(idx),
+            _ => {}
+        }
+    }
+
+    open_brackets.pop()
+}
+
+/// Runs a query that has to fail to parse against an empty database, returning the syntax error's text
+#[cfg(test)]
+pub(crate) fn error_message(query: &str) -> String {
+    match execute_query(&mut Database::new(), query).unwrap_err() {
+        DbErr::Syntax(err) => err.to_string(),
+        err => panic!("Expected a syntax error, got {:?}", err),
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::data_type::DataType;
     use crate::database::Database;
-    use crate::queries::query_parser::execute_query;
+    use crate::errors::DbErr;
+    use crate::queries::query_builder::{get_builders, QueryBuilder};
+    use crate::queries::query_parser::{create_query_plan, error_message, execute_query, execute_script, plan_tokens};
+    use crate::queries::statement_registry::StatementRegistry;
+    use crate::tokenizer::{Span, Tokenizer};
 
     #[test]
     fn test_create_parser() {
@@ -64,7 +202,7 @@ mod tests {
         assert!(result.is_ok());
         assert!(table.is_some());
         assert_eq!(String::from("age"), table.unwrap().columns[0].column_name);
-        assert_eq!(String::from("INTEGER"), table.unwrap().columns[0].column_type);
+        assert_eq!(DataType::Integer, table.unwrap().columns[0].column_type);
     }
 
     #[test]
@@ -77,10 +215,10 @@ mod tests {
         assert!(table.is_some());
 
         assert_eq!(String::from("name"), table.unwrap().columns[0].column_name);
-        assert_eq!(String::from("STRING"), table.unwrap().columns[0].column_type);
+        assert_eq!(DataType::Text, table.unwrap().columns[0].column_type);
 
         assert_eq!(String::from("age"), table.unwrap().columns[1].column_name);
-        assert_eq!(String::from("INTEGER"), table.unwrap().columns[1].column_type);
+        assert_eq!(DataType::Integer, table.unwrap().columns[1].column_type);
     }
 
     #[test]
@@ -95,4 +233,174 @@ mod tests {
         assert!(execute_query(&mut database, "CREATE TABLE apple (one two));").is_err());
         assert!(execute_query(&mut database, "CREATE TABLE apple ((one two);").is_err());
     }
+
+    #[test]
+    fn test_multiple_statements() {
+        let mut database = Database::new();
+        let result = execute_script(&mut database, "CREATE TABLE a (x INTEGER); CREATE TABLE b (y INTEGER);");
+
+        assert_eq!(2, result.results.len());
+        assert_eq!(None, result.failed_statement);
+        assert!(database.get_table("a").is_some());
+        assert!(database.get_table("b").is_some());
+    }
+
+    #[test]
+    fn test_script_stops_at_first_failure() {
+        let mut database = Database::new();
+        let result = execute_script(
+            &mut database,
+            "CREATE TABLE a (x INTEGER);\nCREATE TABLE a (x INTEGER);\nCREATE TABLE b (y INTEGER);",
+        );
+
+        assert_eq!(Some(1), result.failed_statement);
+        assert_eq!(2, result.results.len());
+        assert!(result.results[0].is_ok());
+        assert_eq!(Err(DbErr::TableAlreadyExists), result.results[1]);
+        assert!(database.get_table("b").is_none());
+
+        assert_eq!(
+            DbErr::TableAlreadyExists,
+            execute_query(&mut database, "CREATE TABLE b (y INTEGER); CREATE TABLE b (y INTEGER);").unwrap_err()
+        );
+        assert!(database.get_table("b").is_some());
+    }
+
+    #[test]
+    fn test_semicolons_in_strings_and_comments() {
+        let mut database = Database::new();
+        let result = execute_script(
+            &mut database,
+            "-- setup; script\nCREATE TABLE \"a;b\" (x INTEGER); /* ; */ ;; CREATE TABLE c (`y;` INTEGER);",
+        );
+
+        assert_eq!(None, result.failed_statement);
+        assert_eq!(2, result.results.len());
+        assert!(database.get_table("a;b").is_some());
+        assert_eq!("y;", database.get_table("c").unwrap().columns[0].column_name);
+    }
+
+    #[test]
+    fn test_script_tokenizer_error() {
+        let mut database = Database::new();
+        let result = execute_script(&mut database, "CREATE TABLE a (x INTEGER); CREATE TABLE # (x INTEGER);");
+
+        assert_eq!(Some(1), result.failed_statement);
+        assert!(database.get_table("a").is_some());
+        assert!(execute_script(&mut database, "").results.is_empty());
+    }
+
+    #[test]
+    fn test_plan_error_points_at_token() {
+        let mut database = Database::new();
+        let query = "CREATE TABLE apple\n  (name STRING) garbage;";
+
+        match create_query_plan(&mut database, query).err().unwrap() {
+            DbErr::Syntax(err) => {
+                assert_eq!(
+                    Span {
+                        start: 35,
+                        end: 42,
+                        line: 2,
+                        column: 17
+                    },
+                    err.span
+                );
+                assert!(err.render(query).ends_with("\n  (name STRING) garbage;\n                ^^^^^^^"));
+            }
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_plan_error_at_end_of_query() {
+        let mut database = Database::new();
+
+        match create_query_plan(&mut database, "CREATE TABLE").err().unwrap() {
+            DbErr::Syntax(err) => assert_eq!(
+                "expected one of `IF` or identifier after `TABLE`, but the statement ended at 1:13",
+                err.to_string()
+            ),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(
+            "expected one of `CREATE`, `DELETE`, `DROP`, `INSERT`, `SELECT` or `UPDATE` at 1:1",
+            error_message("users TABLE;")
+        );
+        assert_eq!("expected `TABLE` after `CREATE` at 1:8", error_message("CREATE users;"));
+        assert_eq!(
+            "expected `(` after table name at 1:20",
+            error_message("CREATE TABLE users name string);")
+        );
+        assert_eq!(
+            "expected `;` after column definitions at 1:29",
+            error_message("CREATE TABLE users (name x) garbage;")
+        );
+        assert_eq!("Unable to parse at 1:14", error_message("CREATE TABLE #;"));
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        let query = "CREATE TABLE t (price DECIMAL(10, 2);";
+        match execute_query(&mut Database::new(), query).unwrap_err() {
+            DbErr::Syntax(err) => assert_eq!(
+                "unmatched `(` at 1:16\nCREATE TABLE t (price DECIMAL(10, 2);\n               ^",
+                err.render(query)
+            ),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+
+        assert_eq!("unmatched `)` at 1:23", error_message("CREATE TABLE t (a int)) ;"));
+        assert_eq!("unmatched `(` at 1:26", error_message("CREATE TABLE t (a VARCHAR(2, (3);"));
+        // A failure before the bracket is still reported as it was
+        assert_eq!("expected `TABLE` after `CREATE` at 1:8", error_message("CREATE TABEL t ((a int);"));
+    }
+
+    #[test]
+    fn test_trailing_tokens() {
+        assert_eq!(
+            "expected `;` after table name at 1:20",
+            error_message("DELETE TABLE users garbage garbage;")
+        );
+        assert_eq!(
+            "expected `;` after table name, but the statement ended at 1:19",
+            error_message("DELETE TABLE users")
+        );
+
+        match create_query_plan(&mut Database::new(), "DELETE TABLE a; DELETE TABLE b;")
+            .err()
+            .unwrap()
+        {
+            DbErr::Syntax(err) => assert_eq!("expected end of statement after `;` at 1:17", err.to_string()),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_builders() {
+        let query = "DELETE TABLE users;";
+        let tokens = Tokenizer::new().tokenize_spanned(query).unwrap();
+        let mut registry = StatementRegistry::new();
+        for builder in get_builders() {
+            registry
+                .register(QueryBuilder {
+                    name: format!("DROP ANYTHING {}", builder.name),
+                    ..builder
+                })
+                .unwrap();
+        }
+
+        match plan_tokens(&registry, query, &tokens).err().unwrap() {
+            DbErr::Syntax(err) => assert_eq!(
+                "ambiguous statement, it matches both DELETE TABLE and DROP ANYTHING DELETE TABLE at 1:1",
+                err.to_string()
+            ),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+        assert!(plan_tokens(&StatementRegistry::new(), query, &tokens).is_ok());
+    }
 }
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::field_value::FieldValue;
+
+/// The rows a query returned, with a name for each of their columns
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<FieldValue>>,
+}
+
+impl ResultSet {
+    pub(crate) fn new(columns: Vec<String>) -> ResultSet {
+        ResultSet { columns, rows: Vec::new() }
+    }
+}
+
+/// Lays the rows out as a table, one column per value and one line per row, followed by the row count
+impl fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|value| value.to_string()).collect())
+            .collect();
+
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                cells
+                    .iter()
+                    .map(|row| row[idx].chars().count())
+                    .fold(name.chars().count(), usize::max)
+            })
+            .collect();
+
+        let line = |values: &[String]| {
+            let padded: Vec<String> = values
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{:width$}", value, width = width))
+                .collect();
+            padded.join(" | ").trim_end().to_owned()
+        };
+
+        writeln!(f, "{}", line(&self.columns))?;
+        let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        writeln!(f, "{}", separator.join("-+-"))?;
+        for row in &cells {
+            writeln!(f, "{}", line(row))?;
+        }
+
+        match self.rows.len() {
+            1 => write!(f, "(1 row)"),
+            count => write!(f, "({} rows)", count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{field_value::FieldValue, queries::result_set::ResultSet};
+
+    #[test]
+    fn display_as_table() {
+        let mut result_set = ResultSet::new(vec![String::from("id"), String::from("name")]);
+        result_set
+            .rows
+            .push(vec![FieldValue::Integer(1), FieldValue::Text(String::from("ann"))]);
+        result_set.rows.push(vec![FieldValue::Integer(200), FieldValue::Null]);
+
+        assert_eq!("id  | name\n----+-----\n1   | ann\n200 | NULL\n(2 rows)", result_set.to_string());
+        assert_eq!("a\n-\n(0 rows)", ResultSet::new(vec![String::from("a")]).to_string());
+    }
+}
@@ -0,0 +1,543 @@
+use crate::{
+    ast::{formatter::Formatter, ColumnRef, Expr, Literal, OrderByItem, Select, SelectItem, Statement, TableRef},
+    database::{ColumnDescription, Database},
+    errors::DbErr,
+    evaluator::Evaluator,
+    field_value::FieldValue,
+};
+
+use super::{
+    parse_steps::Captures,
+    result_set::ResultSet,
+    sort::{RowSorter, SortValue},
+    Query, QuerySuccess,
+};
+
+pub(crate) struct SelectQuery {
+    statement: Select,
+}
+
+impl Query for SelectQuery {
+    fn new(mut captures: Captures) -> Result<SelectQuery, DbErr> {
+        let projection = captures
+            .take_repeated("Items")?
+            .into_iter()
+            .map(|mut item| match item.take_choice("Item")? {
+                (0, _) => Ok(SelectItem::Wildcard),
+                (_, mut captured) => Ok(SelectItem::Expr {
+                    expr: captured.take_expression("Value")?,
+                    alias: captured
+                        .take_optional("Alias")?
+                        .map(|mut alias| alias.take_identifier("Alias"))
+                        .transpose()?,
+                }),
+            })
+            .collect::<Result<Vec<SelectItem>, DbErr>>()?;
+
+        let order_by = match captures.take_optional("OrderBy")? {
+            Some(mut order_by) => order_by
+                .take_repeated("Keys")?
+                .into_iter()
+                .map(|mut key| {
+                    Ok(OrderByItem {
+                        expr: key.take_expression("Key")?,
+                        descending: match key.take_optional("Direction")? {
+                            Some(mut direction) => direction.take_choice("Direction")?.0 == 1,
+                            None => false,
+                        },
+                        nulls_first: key
+                            .take_optional("Nulls")?
+                            .map(|mut nulls| Ok::<_, DbErr>(nulls.take_choice("Position")?.0 == 0))
+                            .transpose()?,
+                    })
+                })
+                .collect::<Result<Vec<OrderByItem>, DbErr>>()?,
+            None => Vec::new(),
+        };
+
+        let limit = captures
+            .take_optional("Limit")?
+            .map(|mut limit| row_count(&mut limit, "LIMIT"))
+            .transpose()?;
+        let offset = captures
+            .take_optional("Offset")?
+            .map(|mut offset| row_count(&mut offset, "OFFSET"))
+            .transpose()?;
+        // `FETCH FIRST ROWS ONLY` fetches a single row
+        let fetch = match captures.take_optional("Fetch")? {
+            Some(mut fetch) => match fetch.take_optional("Count")? {
+                Some(mut count) => Some(row_count(&mut count, "FETCH FIRST")?),
+                None => Some(1),
+            },
+            None => None,
+        };
+        if limit.is_some() && fetch.is_some() {
+            return Err(DbErr::Generic(String::from("LIMIT and FETCH FIRST can't be used together")));
+        }
+
+        Ok(SelectQuery {
+            statement: Select {
+                projection,
+                from: captures
+                    .take_optional("From")?
+                    .map(|mut from| Ok::<_, DbErr>(TableRef::new(&from.take_identifier("Name")?)))
+                    .transpose()?,
+                selection: captures
+                    .take_optional("Where")?
+                    .map(|mut selection| selection.take_expression("Condition"))
+                    .transpose()?,
+                order_by,
+                limit: limit.or(fetch),
+                offset,
+            },
+        })
+    }
+
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        let table = match &self.statement.from {
+            Some(table) => Some(database.get_table(&table.name).ok_or(DbErr::TableNotExists)?),
+            None => None,
+        };
+        // Without a table there is a single row, with no columns
+        let (columns, rows): (&[ColumnDescription], Vec<&[FieldValue]>) = match table {
+            Some(table) => (&table.columns, table.rows.entries().map(|row| row.fields.as_slice()).collect()),
+            None => (&[], vec![&[]]),
+        };
+
+        let evaluator = Evaluator::new(self.statement.from.as_ref().map(|table| table.name.as_str()), columns);
+        // Unknown columns are reported even if there are no rows to evaluate them for
+        for expr in self.expressions() {
+            evaluator.check_columns(expr)?;
+        }
+
+        let mut result_set = ResultSet::new(self.column_names(columns)?);
+        let sort_keys = self.sort_keys(&evaluator, &result_set.columns)?;
+
+        // The rows before the offset are sorted along with the rest, and dropped at the end
+        let offset = self.statement.offset.unwrap_or(0);
+        let end = self.statement.limit.map(|limit| offset.saturating_add(limit));
+        let mut sorter = RowSorter::new(end);
+        for row in rows {
+            // Without an order any rows will do, so reading stops as soon as there are enough
+            if sort_keys.is_empty() && end.is_some_and(|end| sorter.len() >= end) {
+                break;
+            }
+            if let Some(condition) = &self.statement.selection {
+                if !evaluator.matches(condition, row)? {
+                    continue;
+                }
+            }
+
+            let mut values: Vec<FieldValue> = Vec::new();
+            for item in &self.statement.projection {
+                match item {
+                    SelectItem::Wildcard => values.extend_from_slice(row),
+                    SelectItem::Expr { expr, .. } => values.push(evaluator.evaluate(expr, row)?),
+                }
+            }
+
+            let mut keys: Vec<SortValue> = Vec::new();
+            for (key, item) in sort_keys.iter().zip(&self.statement.order_by) {
+                let value = match key {
+                    SortKey::Output(idx) => values[*idx].clone(),
+                    SortKey::Expr(expr) => evaluator.evaluate(expr, row)?,
+                };
+                keys.push(SortValue::new(value, item));
+            }
+            sorter.push(keys, values);
+        }
+        result_set.rows = sorter.finish().into_iter().skip(offset).collect();
+
+        Ok(QuerySuccess::Rows(result_set))
+    }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::Select(self.statement.clone()))
+    }
+
+    fn bind(&self, _database: &Database, arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        let projection = self
+            .statement
+            .projection
+            .iter()
+            .map(|item| match item {
+                SelectItem::Wildcard => SelectItem::Wildcard,
+                SelectItem::Expr { expr, alias } => SelectItem::Expr {
+                    expr: expr.bind(arguments),
+                    alias: alias.clone(),
+                },
+            })
+            .collect();
+        let order_by = self
+            .statement
+            .order_by
+            .iter()
+            .map(|item| OrderByItem {
+                expr: item.expr.bind(arguments),
+                ..item.clone()
+            })
+            .collect();
+
+        Ok(Box::new(SelectQuery {
+            statement: Select {
+                projection,
+                selection: self.statement.selection.as_ref().map(|condition| condition.bind(arguments)),
+                order_by,
+                ..self.statement.clone()
+            },
+        }))
+    }
+}
+
+impl SelectQuery {
+    /// Every expression the query evaluates
+    fn expressions(&self) -> impl Iterator<Item = &Expr> {
+        let projected = self.statement.projection.iter().filter_map(|item| match item {
+            SelectItem::Wildcard => None,
+            SelectItem::Expr { expr, .. } => Some(expr),
+        });
+
+        projected.chain(&self.statement.selection)
+    }
+
+    /// Names each result column by its alias, the column it reads, or otherwise the expression itself
+    fn column_names(&self, columns: &[ColumnDescription]) -> Result<Vec<String>, DbErr> {
+        let formatter = Formatter::new();
+        let mut names: Vec<String> = Vec::new();
+
+        for item in &self.statement.projection {
+            match item {
+                SelectItem::Wildcard if self.statement.from.is_none() => {
+                    return Err(DbErr::Generic(String::from("SELECT * needs a table to select from")));
+                }
+                SelectItem::Wildcard => names.extend(columns.iter().map(|column| column.column_name.clone())),
+                SelectItem::Expr { alias: Some(alias), .. } => names.push(alias.clone()),
+                SelectItem::Expr {
+                    expr: Expr::Column(column),
+                    alias: None,
+                } => names.push(column.column.clone()),
+                SelectItem::Expr { expr, alias: None } => names.push(formatter.format_expression(expr)),
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Resolves what each `ORDER BY` key sorts on. A number is the position of a result column, and a bare
+    /// name is the result column of that name, such as an alias. Anything else, including a name several
+    /// result columns share, is evaluated against the table's row.
+    fn sort_keys(&self, evaluator: &Evaluator, names: &[String]) -> Result<Vec<SortKey<'_>>, DbErr> {
+        let mut keys: Vec<SortKey> = Vec::new();
+
+        for item in &self.statement.order_by {
+            let key = match &item.expr {
+                Expr::Literal(Literal::Integer(position)) => usize::try_from(*position)
+                    .ok()
+                    .filter(|position| (1..=names.len()).contains(position))
+                    .map(|position| SortKey::Output(position - 1))
+                    .ok_or_else(|| DbErr::Generic(format!("ORDER BY position {} is not in the select list", position)))?,
+                Expr::Column(ColumnRef { table: None, column }) if names.iter().filter(|name| *name == column).count() == 1 => {
+                    SortKey::Output(names.iter().position(|name| name == column).unwrap())
+                }
+                expr => {
+                    evaluator.check_columns(expr)?;
+                    SortKey::Expr(expr)
+                }
+            };
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+}
+
+/// What an `ORDER BY` key sorts on
+enum SortKey<'a> {
+    /// A column of the result
+    Output(usize),
+    Expr(&'a Expr),
+}
+
+/// The number of rows in a `LIMIT`, `OFFSET` or `FETCH FIRST`
+fn row_count(captures: &mut Captures, clause: &str) -> Result<usize, DbErr> {
+    let count = captures.take_integer("Count")?;
+    usize::try_from(count).map_err(|_| DbErr::Generic(format!("{} can't be negative, got {}", clause, count)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::{
+            query_parser::{self, error_message},
+            result_set::ResultSet,
+            QuerySuccess,
+        },
+    };
+
+    fn database() -> Database {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE users (id INTEGER, name TEXT, age INTEGER);
+             INSERT INTO users VALUES (1, 'ann', 31), (2, 'bob', NULL), (3, 'cy', 17);",
+        )
+        .unwrap();
+        database
+    }
+
+    fn select(database: &mut Database, query: &str) -> ResultSet {
+        match query_parser::execute_query(database, query) {
+            Ok(QuerySuccess::Rows(result_set)) => result_set,
+            result => panic!("Expected rows, got {:?}", result),
+        }
+    }
+
+    fn text(value: &str) -> FieldValue {
+        FieldValue::Text(value.to_owned())
+    }
+
+    #[test]
+    fn select_everything() {
+        let result_set = select(&mut database(), "SELECT * FROM users;");
+
+        assert_eq!(vec!["id", "name", "age"], result_set.columns);
+        assert_eq!(3, result_set.rows.len());
+        assert_eq!(vec![FieldValue::Integer(2), text("bob"), FieldValue::Null], result_set.rows[1]);
+    }
+
+    #[test]
+    fn projections_and_aliases() {
+        let result_set = select(
+            &mut database(),
+            "SELECT name, age + 1 AS next_age, users.id * 10 tens, name || '!', * FROM users;",
+        );
+
+        assert_eq!(
+            vec!["name", "next_age", "tens", "name || '!'", "id", "name", "age"],
+            result_set.columns
+        );
+        assert_eq!(
+            vec![
+                text("ann"),
+                FieldValue::Integer(32),
+                FieldValue::Integer(10),
+                text("ann!"),
+                FieldValue::Integer(1),
+                text("ann"),
+                FieldValue::Integer(31)
+            ],
+            result_set.rows[0]
+        );
+        assert_eq!(FieldValue::Null, result_set.rows[1][1]);
+    }
+
+    #[test]
+    fn where_keeps_only_true_rows() {
+        let mut database = database();
+        let names = |result_set: ResultSet| -> Vec<FieldValue> { result_set.rows.into_iter().map(|row| row[0].clone()).collect() };
+
+        assert_eq!(
+            vec![text("ann")],
+            names(select(&mut database, "SELECT name FROM users WHERE age > 18;"))
+        );
+        assert_eq!(
+            vec![text("ann"), text("cy")],
+            names(select(&mut database, "SELECT name FROM users WHERE NOT age IS NULL;"))
+        );
+        assert_eq!(
+            vec![text("bob"), text("cy")],
+            names(select(&mut database, "SELECT name FROM users WHERE age IS NULL OR id IN (3, 4);"))
+        );
+        assert_eq!(
+            vec![text("cy")],
+            names(select(&mut database, "SELECT name FROM users WHERE age BETWEEN 0 AND 20;"))
+        );
+        assert!(select(&mut database, "SELECT name FROM users WHERE FALSE;").rows.is_empty());
+    }
+
+    #[test]
+    fn select_without_table() {
+        let result_set = select(&mut Database::new(), "SELECT 1 + 2, 'a' AS letter WHERE TRUE;");
+
+        assert_eq!(vec!["1 + 2", "letter"], result_set.columns);
+        assert_eq!(vec![vec![FieldValue::Integer(3), text("a")]], result_set.rows);
+    }
+
+    #[test]
+    fn order_by() {
+        let mut database = database();
+        query_parser::execute_query(&mut database, "INSERT INTO users VALUES (4, 'dee', 31);").unwrap();
+        let ids = |result_set: ResultSet| -> Vec<i64> {
+            result_set
+                .rows
+                .into_iter()
+                .map(|row| match row[0] {
+                    FieldValue::Integer(id) => id,
+                    ref value => panic!("Expected an id, got {:?}", value),
+                })
+                .collect()
+        };
+
+        // NULL sorts before every value, and rows with equal keys keep their order
+        assert_eq!(vec![2, 3, 1, 4], ids(select(&mut database, "SELECT id FROM users ORDER BY age;")));
+        assert_eq!(
+            vec![1, 4, 3, 2],
+            ids(select(&mut database, "SELECT id FROM users ORDER BY age DESC;"))
+        );
+        assert_eq!(
+            vec![3, 1, 4, 2],
+            ids(select(&mut database, "SELECT id FROM users ORDER BY age ASC NULLS LAST;"))
+        );
+        assert_eq!(
+            vec![2, 4, 1, 3],
+            ids(select(
+                &mut database,
+                "SELECT id FROM users ORDER BY age DESC NULLS FIRST, name DESC;"
+            ))
+        );
+
+        // Keys may name result columns, by alias or position, or be any expression over the row
+        assert_eq!(
+            vec![1, 4, 3, 2],
+            ids(select(
+                &mut database,
+                "SELECT id, age * 2 AS twice FROM users ORDER BY twice DESC, id;"
+            ))
+        );
+        assert_eq!(
+            vec![3, 1, 4, 2],
+            ids(select(&mut database, "SELECT id, age FROM users ORDER BY 2 NULLS LAST, 1;"))
+        );
+        assert_eq!(
+            vec![2, 4, 1, 3],
+            ids(select(&mut database, "SELECT id FROM users ORDER BY id % 2, id;"))
+        );
+        assert_eq!(
+            vec![4, 3, 2, 1],
+            ids(select(&mut database, "SELECT *, id FROM users ORDER BY id DESC;"))
+        );
+    }
+
+    #[test]
+    fn limit_and_offset() {
+        let mut database = database();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE numbers (n INTEGER);
+             INSERT INTO numbers VALUES (5), (3), (8), (1), (9), (2), (7);",
+        )
+        .unwrap();
+        let numbers = |database: &mut Database, query: &str| -> Vec<FieldValue> {
+            select(database, query).rows.into_iter().map(|row| row[0].clone()).collect()
+        };
+        let expected = |values: &[i64]| -> Vec<FieldValue> { values.iter().map(|value| FieldValue::Integer(*value)).collect() };
+
+        assert_eq!(
+            expected(&[1, 2, 3]),
+            numbers(&mut database, "SELECT n FROM numbers ORDER BY n LIMIT 3;")
+        );
+        assert_eq!(
+            expected(&[5, 3]),
+            numbers(&mut database, "SELECT n FROM numbers ORDER BY n DESC LIMIT 2 OFFSET 3;")
+        );
+        assert_eq!(
+            expected(&[7, 8, 9]),
+            numbers(&mut database, "SELECT n FROM numbers ORDER BY n OFFSET 4 ROWS;")
+        );
+        assert_eq!(
+            expected(&[3, 5]),
+            numbers(&mut database, "SELECT n FROM numbers ORDER BY n OFFSET 2 FETCH NEXT 2 ROWS ONLY;")
+        );
+        assert_eq!(
+            expected(&[9]),
+            numbers(&mut database, "SELECT n FROM numbers ORDER BY n DESC FETCH FIRST ROW ONLY;")
+        );
+
+        // Without an order, rows come in the order they are stored
+        assert_eq!(
+            expected(&[8, 1]),
+            numbers(&mut database, "SELECT n FROM numbers WHERE n > 2 OR n < 2 LIMIT 2 OFFSET 2;")
+        );
+        assert!(numbers(&mut database, "SELECT n FROM numbers LIMIT 0;").is_empty());
+        assert!(numbers(&mut database, "SELECT n FROM numbers ORDER BY n OFFSET 10;").is_empty());
+    }
+
+    #[test]
+    fn invalid_order_and_limit() {
+        let mut database = database();
+
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("missing")),
+            query_parser::execute_query(&mut database, "SELECT id FROM users ORDER BY missing;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Generic(String::from("ORDER BY position 3 is not in the select list")),
+            query_parser::execute_query(&mut database, "SELECT id, age FROM users ORDER BY 3;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Generic(String::from("LIMIT and FETCH FIRST can't be used together")),
+            query_parser::execute_query(&mut database, "SELECT id FROM users LIMIT 1 FETCH FIRST 2 ROWS ONLY;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ArithmeticError(String::from("Division by zero")),
+            query_parser::execute_query(&mut database, "SELECT id FROM users ORDER BY 1 / (id - 2) LIMIT 1;").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn invalid_selects() {
+        let mut database = database();
+        query_parser::execute_query(&mut database, "CREATE TABLE empty (a INTEGER);").unwrap();
+
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("b")),
+            query_parser::execute_query(&mut database, "SELECT b FROM empty;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("a")),
+            query_parser::execute_query(&mut database, "SELECT * FROM empty WHERE other.a = 1;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TableNotExists,
+            query_parser::execute_query(&mut database, "SELECT * FROM missing;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Generic(String::from("SELECT * needs a table to select from")),
+            query_parser::execute_query(&mut database, "SELECT *;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TypeMismatch(String::from("Expected a BOOLEAN, got INTEGER")),
+            query_parser::execute_query(&mut database, "SELECT * FROM users WHERE id;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ArithmeticError(String::from("Division by zero")),
+            query_parser::execute_query(&mut database, "SELECT id / (id - 2) FROM users;").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn malformed_selects() {
+        assert_eq!(
+            "expected one of `*` or expression after `SELECT` at 1:8",
+            error_message("SELECT FROM t;")
+        );
+        assert_eq!("expected identifier after `AS` at 1:13", error_message("SELECT a AS 1 FROM t;"));
+        assert_eq!(
+            "expected expression after `WHERE` at 1:23",
+            error_message("SELECT * FROM t WHERE ;")
+        );
+        assert_eq!("expected `BY` after `ORDER` at 1:23", error_message("SELECT * FROM t ORDER a;"));
+        assert_eq!(
+            "expected one of `FIRST` or `LAST` after `NULLS` at 1:33",
+            error_message("SELECT * FROM t ORDER BY a NULLS;")
+        );
+        assert_eq!("expected integer after `LIMIT` at 1:23", error_message("SELECT * FROM t LIMIT a;"));
+        assert_eq!(
+            "expected `ONLY` after `ROWS` at 1:35",
+            error_message("SELECT * FROM t FETCH FIRST 2 ROWS;")
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::{ast::OrderByItem, field_value::FieldValue};
+
+/// Limits up to this many rows are served from a heap of the best rows so far, rather than by sorting every row
+const MAX_HEAP_ROWS: usize = 1000;
+
+/// One value a row is sorted by, which knows the direction of its key
+#[derive(Debug)]
+pub(crate) struct SortValue {
+    value: FieldValue,
+    descending: bool,
+    nulls_first: bool,
+}
+
+impl SortValue {
+    pub(crate) fn new(value: FieldValue, item: &OrderByItem) -> SortValue {
+        SortValue {
+            value,
+            descending: item.descending,
+            nulls_first: item.nulls_first(),
+        }
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &SortValue) -> Ordering {
+        match (self.value.is_null(), other.value.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if self.descending => other.value.cmp(&self.value),
+            (false, false) => self.value.cmp(&other.value),
+        }
+    }
+}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &SortValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &SortValue) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+/// A row with the values it is sorted by. Rows with equal keys keep the order they were added in.
+#[derive(Debug)]
+struct SortedRow {
+    keys: Vec<SortValue>,
+    position: usize,
+    values: Vec<FieldValue>,
+}
+
+impl Ord for SortedRow {
+    fn cmp(&self, other: &SortedRow) -> Ordering {
+        self.keys.cmp(&other.keys).then(self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for SortedRow {
+    fn partial_cmp(&self, other: &SortedRow) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortedRow {
+    fn eq(&self, other: &SortedRow) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortedRow {}
+
+enum SortedRows {
+    /// Every row, sorted once they have all been added
+    All(Vec<SortedRow>),
+    /// Only the rows that sort first, with the last of them on top, to be dropped when a row sorts before it
+    TopN(BinaryHeap<SortedRow>),
+}
+
+/// Collects rows one by one and returns them in order, keeping only the first `limit` when there is one
+pub(crate) struct RowSorter {
+    rows: SortedRows,
+    limit: Option<usize>,
+    added: usize,
+}
+
+impl RowSorter {
+    pub(crate) fn new(limit: Option<usize>) -> RowSorter {
+        let rows = match limit {
+            Some(limit) if limit <= MAX_HEAP_ROWS => SortedRows::TopN(BinaryHeap::with_capacity(limit)),
+            _ => SortedRows::All(Vec::new()),
+        };
+
+        RowSorter { rows, limit, added: 0 }
+    }
+
+    /// The number of rows that are kept
+    pub(crate) fn len(&self) -> usize {
+        match &self.rows {
+            SortedRows::All(rows) => rows.len(),
+            SortedRows::TopN(heap) => heap.len(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, keys: Vec<SortValue>, values: Vec<FieldValue>) {
+        let row = SortedRow {
+            keys,
+            position: self.added,
+            values,
+        };
+        self.added += 1;
+
+        match &mut self.rows {
+            SortedRows::All(rows) => rows.push(row),
+            SortedRows::TopN(heap) if Some(heap.len()) < self.limit => heap.push(row),
+            SortedRows::TopN(heap) => {
+                if let Some(mut last) = heap.peek_mut() {
+                    if row < *last {
+                        *last = row;
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Vec<FieldValue>> {
+        let sorted = match self.rows {
+            SortedRows::All(mut rows) => {
+                // Positions are unique, so an unstable sort still keeps equal keys in order
+                rows.sort_unstable();
+                rows
+            }
+            SortedRows::TopN(heap) => heap.into_sorted_vec(),
+        };
+
+        sorted
+            .into_iter()
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|row| row.values)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{ColumnRef, Expr, OrderByItem},
+        field_value::FieldValue,
+        queries::sort::{RowSorter, SortValue, MAX_HEAP_ROWS},
+    };
+
+    fn order_by(descending: bool, nulls_first: Option<bool>) -> OrderByItem {
+        OrderByItem {
+            expr: Expr::Column(ColumnRef {
+                table: None,
+                column: String::from("a"),
+            }),
+            descending,
+            nulls_first,
+        }
+    }
+
+    /// Sorts `count` rows with repeated keys and some NULLs, returning each row's position in the input
+    fn sort(count: usize, item: &OrderByItem, limit: Option<usize>) -> Vec<FieldValue> {
+        let mut sorter = RowSorter::new(limit);
+        for position in 0..count {
+            let key = match (position * 7919) % 13 {
+                0 => FieldValue::Null,
+                key => FieldValue::Integer(key as i64 % 5),
+            };
+            sorter.push(vec![SortValue::new(key, item)], vec![FieldValue::Integer(position as i64)]);
+        }
+        sorter.finish().into_iter().map(|mut row| row.remove(0)).collect()
+    }
+
+    #[test]
+    fn nulls_follow_the_direction() {
+        let keys = |item: &OrderByItem| -> Vec<FieldValue> {
+            let mut sorter = RowSorter::new(None);
+            for key in [FieldValue::Integer(2), FieldValue::Null, FieldValue::Integer(1)] {
+                sorter.push(vec![SortValue::new(key.clone(), item)], vec![key]);
+            }
+            sorter.finish().into_iter().map(|mut row| row.remove(0)).collect()
+        };
+        let (one, two) = (FieldValue::Integer(1), FieldValue::Integer(2));
+
+        assert_eq!(vec![FieldValue::Null, one.clone(), two.clone()], keys(&order_by(false, None)));
+        assert_eq!(vec![two.clone(), one.clone(), FieldValue::Null], keys(&order_by(true, None)));
+        assert_eq!(
+            vec![one.clone(), two.clone(), FieldValue::Null],
+            keys(&order_by(false, Some(false)))
+        );
+        assert_eq!(vec![FieldValue::Null, two, one], keys(&order_by(true, Some(true))));
+    }
+
+    #[test]
+    fn top_n_matches_a_full_sort() {
+        for item in [order_by(false, None), order_by(true, None), order_by(true, Some(true))] {
+            let sorted = sort(5000, &item, None);
+
+            for limit in [0, 1, 10, MAX_HEAP_ROWS, MAX_HEAP_ROWS + 1] {
+                assert_eq!(sorted[..limit], sort(5000, &item, Some(limit)), "limit {}", limit);
+            }
+            // A limit beyond the number of rows keeps every row
+            assert_eq!(sort(40, &item, None), sort(40, &item, Some(100)));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::rc::Rc;
+
+use crate::{database::Database, errors::DbErr, tokenizer::Tokenizer};
+
+use super::query_builder::{self, QueryBuilder};
+
+/// The statements a database understands, along with the tokenizer that reads them. A registry is built
+/// once per database, extended with custom statements at startup, and shared by every query after that.
+pub struct StatementRegistry {
+    tokenizer: Tokenizer,
+    builders: Vec<QueryBuilder>,
+}
+
+impl StatementRegistry {
+    /// A registry of the built-in statements
+    pub(crate) fn new() -> StatementRegistry {
+        StatementRegistry {
+            tokenizer: Tokenizer::new(),
+            builders: query_builder::get_builders(),
+        }
+    }
+
+    /// Adds a statement. Its name has to be unique, and its grammar shouldn't overlap with any other
+    /// statement's, or matching queries are rejected as ambiguous.
+    pub(crate) fn register(&mut self, builder: QueryBuilder) -> Result<(), DbErr> {
+        if self.builders.iter().any(|existing| existing.name == builder.name) {
+            return Err(DbErr::Generic(format!("A statement named {} is already registered", builder.name)));
+        }
+
+        self.builders.push(builder);
+        Ok(())
+    }
+
+    pub(crate) fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    pub(crate) fn builders(&self) -> &[QueryBuilder] {
+        &self.builders
+    }
+
+    /// The EBNF syntax of every statement whose name starts with the words in `statement`, such as `create`
+    /// or `DELETE TABLE`. Every statement matches when `statement` is empty.
+    pub(crate) fn syntax(&self, statement: &str) -> Vec<String> {
+        let words: Vec<String> = statement.split_whitespace().map(|word| word.to_uppercase()).collect();
+
+        self.builders
+            .iter()
+            .filter(|builder| {
+                let name: Vec<String> = builder.name.split_whitespace().map(|word| word.to_uppercase()).collect();
+                name.starts_with(&words)
+            })
+            .map(|builder| builder.steps.to_ebnf(&builder.name))
+            .collect()
+    }
+}
+
+impl Database {
+    /// Adds a custom statement, which every query run after this can use. Statements have to be registered
+    /// at startup, before the database hands its registry to a running query.
+    pub fn register_statement(&mut self, builder: QueryBuilder) -> Result<(), DbErr> {
+        match Rc::get_mut(&mut self.registry) {
+            Some(registry) => registry.register(builder),
+            None => Err(DbErr::Generic(String::from("Can't register statements while a query is running"))),
+        }
+    }
+
+    /// The syntax of the statements named by `statement`, as shown by the shell's `HELP` command
+    pub fn syntax_help(&self, statement: &str) -> Result<String, DbErr> {
+        let syntax = self.registry.syntax(statement);
+        if syntax.is_empty() {
+            let known: Vec<&str> = self.registry.builders().iter().map(|builder| builder.name.as_str()).collect();
+            return Err(DbErr::Generic(format!(
+                "Unknown statement {}, the known statements are {}",
+                statement.trim(),
+                known.join(", ")
+            )));
+        }
+
+        Ok(syntax.join("\n\n"))
+    }
+
+    pub(crate) fn registry(&self) -> Rc<StatementRegistry> {
+        Rc::clone(&self.registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        queries::{
+            parse_steps::{Captures, ParseSteps},
+            query_builder::QueryBuilder,
+            query_parser, Query, QuerySuccess,
+        },
+        tokenizer::Token,
+    };
+
+    /// Checks that a table exists, standing in for an application specific statement
+    struct ReindexQuery {
+        table: String,
+    }
+
+    impl Query for ReindexQuery {
+        fn new(mut captures: Captures) -> Result<ReindexQuery, DbErr> {
+            Ok(ReindexQuery {
+                table: captures.take_identifier("Name")?,
+            })
+        }
+
+        fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+            match database.get_table(&self.table) {
+                Some(_) => Ok(QuerySuccess::Done),
+                None => Err(DbErr::TableNotExists),
+            }
+        }
+    }
+
+    fn reindex_builder() -> QueryBuilder {
+        QueryBuilder {
+            name: String::from("REINDEX"),
+            steps: ParseSteps::new()
+                .add_keyword("reindex")
+                .add_named_step(Token::Identifier("_".into()), "Name")
+                .described("table name")
+                .add_step(Token::SemiColon),
+            factory: Box::new(|captures| Ok(Box::new(ReindexQuery::new(captures)?))),
+        }
+    }
+
+    fn error_message(database: &mut Database, query: &str) -> String {
+        match query_parser::execute_query(database, query).unwrap_err() {
+            DbErr::Syntax(err) => err.to_string(),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn custom_statement() {
+        let mut database = Database::new();
+        database.register_statement(reindex_builder()).unwrap();
+
+        assert!(query_parser::execute_query(&mut database, "CREATE TABLE users (a int); Reindex users;").is_ok());
+        assert_eq!(
+            DbErr::TableNotExists,
+            query_parser::execute_query(&mut database, "REINDEX missing;").unwrap_err()
+        );
+        assert_eq!(
+            "expected one of `CREATE`, `DELETE`, `DROP`, `INSERT`, `SELECT`, `UPDATE` or `REINDEX` at 1:1",
+            error_message(&mut database, "users TABLE;")
+        );
+        assert_eq!(
+            "expected identifier after `REINDEX` at 1:9",
+            error_message(&mut database, "REINDEX ;")
+        );
+    }
+
+    #[test]
+    fn custom_statements_belong_to_one_database() {
+        let mut database = Database::new();
+        database.register_statement(reindex_builder()).unwrap();
+
+        let mut other = Database::new();
+        other.create_table("users", Vec::new()).unwrap();
+        assert!(query_parser::execute_query(&mut other, "REINDEX users;").is_err());
+    }
+
+    #[test]
+    fn syntax_of_builtin_statements() {
+        let database = Database::new();
+
+        assert_eq!(
+            "create_table = \"CREATE\" \"TABLE\" [ \"IF\" \"NOT\" \"EXISTS\" ] table_name \"(\" table_element { \",\" table_element } \")\" \";\" ;\n\
+             table_element = column_name column_type [ type_parameters ] { column_constraint } | [ \"CONSTRAINT\" constraint_name ] \
+             ( \"PRIMARY\" \"KEY\" \"(\" column_name { \",\" column_name } \")\" | \"UNIQUE\" \"(\" column_name { \",\" column_name } \")\" \
+             | \"CHECK\" \"(\" expression \")\" ) ;\n\
+             type_parameters = \"(\" integer { \",\" integer } \")\" ;\n\
+             column_constraint = [ \"CONSTRAINT\" constraint_name ] \
+             ( \"NOT\" \"NULL\" | \"DEFAULT\" expression | \"UNIQUE\" | \"PRIMARY\" \"KEY\" | \"CHECK\" \"(\" expression \")\" ) ;",
+            database.syntax_help("create table").unwrap()
+        );
+        assert_eq!(
+            "delete_table = ( \"DELETE\" | \"DROP\" ) \"TABLE\" [ \"IF\" \"EXISTS\" ] table_name \";\" ;\n\n\
+             delete = \"DELETE\" \"FROM\" table_name [ \"WHERE\" expression ] \";\" ;",
+            database.syntax_help("DELETE").unwrap()
+        );
+        assert_eq!(6, database.syntax_help("").unwrap().split("\n\n").count());
+        assert_eq!(
+            DbErr::Generic(String::from(
+                "Unknown statement TABLE, the known statements are CREATE TABLE, DELETE TABLE, INSERT, SELECT, UPDATE, DELETE"
+            )),
+            database.syntax_help("TABLE").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn syntax_of_custom_statements() {
+        let mut database = Database::new();
+        database.register_statement(reindex_builder()).unwrap();
+
+        assert_eq!("reindex = \"REINDEX\" table_name \";\" ;", database.syntax_help("reindex").unwrap());
+    }
+
+    #[test]
+    fn duplicate_statement_names() {
+        let mut database = Database::new();
+
+        database.register_statement(reindex_builder()).unwrap();
+        assert!(database.register_statement(reindex_builder()).is_err());
+    }
+}
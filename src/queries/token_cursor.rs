@@ -0,0 +1,137 @@
+use std::mem;
+
+use crate::tokenizer::{SpannedToken, Token};
+
+use super::parse_steps::ParseFailure;
+
+/// A position within the tokens of one statement. Grammar steps and the recursive descent parsers
+/// they hand control to share the cursor, so a failure anywhere can report how far parsing got.
+pub struct TokenCursor<'a> {
+    tokens: &'a [SpannedToken],
+    position: usize,
+}
+
+impl<'a> TokenCursor<'a> {
+    pub(crate) fn new(tokens: &'a [SpannedToken]) -> TokenCursor<'a> {
+        TokenCursor { tokens, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Moves back to an earlier position, so another grammar can be tried from there
+    pub fn rewind(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    pub fn peek(&self) -> Option<&'a Token> {
+        self.peek_nth(0)
+    }
+
+    /// Looks `n` tokens past the next one without consuming anything
+    pub fn peek_nth(&self, n: usize) -> Option<&'a Token> {
+        self.tokens.get(self.position + n).map(|spanned| &spanned.token)
+    }
+
+    pub fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.peek()?;
+        self.position += 1;
+        Some(token)
+    }
+
+    /// Whether the next token is the same kind of token as `expected`, ignoring any value either carries
+    pub fn check(&self, expected: &Token) -> bool {
+        self.peek()
+            .is_some_and(|token| mem::discriminant(token) == mem::discriminant(expected))
+    }
+
+    /// Consumes the next token if it is the same kind as `expected`
+    pub fn eat(&mut self, expected: &Token) -> bool {
+        let matched = self.check(expected);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    /// Consumes the next token, failing unless it is the same kind as `expected`
+    pub fn expect(&mut self, expected: &Token) -> Result<&'a Token, ParseFailure> {
+        if self.check(expected) {
+            Ok(self.advance().unwrap())
+        } else {
+            Err(self.failure(vec![expected.describe()]))
+        }
+    }
+
+    pub fn expect_identifier(&mut self) -> Result<String, ParseFailure> {
+        match self.expect(&Token::Identifier(String::new()))? {
+            Token::Identifier(name) => Ok(name.clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// A failure at the next token, described as coming after the token before it
+    pub fn failure(&self, expected: Vec<String>) -> ParseFailure {
+        ParseFailure {
+            position: self.position,
+            expected,
+            context: self.position.checked_sub(1).map(|previous| self.tokens[previous].token.describe()),
+            unclosed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        queries::{parse_steps::ParseFailure, token_cursor::TokenCursor},
+        tokenizer::{Token, Tokenizer},
+    };
+
+    #[test]
+    fn peek_and_advance() {
+        let tokens = Tokenizer::new().tokenize_spanned("CREATE TABLE users").unwrap();
+        let mut cursor = TokenCursor::new(&tokens);
+
+        assert_eq!(Some(&Token::Table), cursor.peek_nth(1));
+        assert_eq!(Some(&Token::Create), cursor.advance());
+        assert!(!cursor.eat(&Token::Create));
+        assert!(cursor.eat(&Token::Table));
+        assert_eq!(Ok(String::from("users")), cursor.expect_identifier());
+        assert!(cursor.is_at_end());
+        assert_eq!(None, cursor.advance());
+    }
+
+    #[test]
+    fn expect_failure() {
+        let tokens = Tokenizer::new().tokenize_spanned("CREATE users").unwrap();
+        let mut cursor = TokenCursor::new(&tokens);
+
+        assert_eq!(
+            ParseFailure {
+                position: 0,
+                expected: vec![String::from("`TABLE`")],
+                context: None,
+                unclosed: None
+            },
+            cursor.expect(&Token::Table).unwrap_err()
+        );
+
+        cursor.advance();
+        assert_eq!(
+            ParseFailure {
+                position: 1,
+                expected: vec![String::from("`TABLE`")],
+                context: Some(String::from("`CREATE`")),
+                unclosed: None
+            },
+            cursor.expect(&Token::Table).unwrap_err()
+        );
+        assert_eq!(1, cursor.position());
+    }
+}
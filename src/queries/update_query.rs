@@ -0,0 +1,305 @@
+use crate::{
+    ast::{Assignment, Statement, TableRef, Update},
+    database::{Database, Table},
+    errors::DbErr,
+    evaluator::Evaluator,
+    field_value::FieldValue,
+    row_store::RowId,
+};
+
+use super::{parse_steps::Captures, prepared_statement, Query, QuerySuccess};
+
+pub(crate) struct UpdateQuery {
+    statement: Update,
+}
+
+impl Query for UpdateQuery {
+    fn new(mut captures: Captures) -> Result<UpdateQuery, DbErr> {
+        let assignments = captures
+            .take_repeated("Assignments")?
+            .into_iter()
+            .map(|mut assignment| {
+                Ok(Assignment {
+                    column: assignment.take_identifier("Column")?,
+                    value: assignment.take_expression("Value")?,
+                })
+            })
+            .collect::<Result<Vec<Assignment>, DbErr>>()?;
+
+        Ok(UpdateQuery {
+            statement: Update {
+                table: TableRef::new(&captures.take_identifier("Name")?),
+                assignments,
+                selection: captures
+                    .take_optional("Where")?
+                    .map(|mut selection| selection.take_expression("Condition"))
+                    .transpose()?,
+            },
+        })
+    }
+
+    fn execute(&self, database: &mut Database) -> Result<QuerySuccess, DbErr> {
+        let table_name = &self.statement.table.name;
+        let table = database.get_table_mut(table_name).ok_or(DbErr::TableNotExists)?;
+
+        let updates = self.updated_rows(table)?;
+        let count = updates.len();
+
+        let mut applied: Vec<(RowId, Vec<FieldValue>)> = Vec::new();
+        for RowUpdate {
+            row_id,
+            old_values,
+            new_values,
+        } in updates
+        {
+            if let Err(err) = table.update_row(row_id, new_values) {
+                // One row failing leaves every row as it was
+                for (row_id, old_values) in applied.into_iter().rev() {
+                    table.replace_row(row_id, old_values);
+                }
+                return Err(err);
+            }
+            applied.push((row_id, old_values));
+        }
+
+        Ok(QuerySuccess::RowsAffected(count))
+    }
+
+    fn statement(&self) -> Option<Statement> {
+        Some(Statement::Update(self.statement.clone()))
+    }
+
+    fn bind(&self, database: &Database, arguments: &[FieldValue]) -> Result<Box<dyn Query>, DbErr> {
+        let table = database.get_table(&self.statement.table.name).ok_or(DbErr::TableNotExists)?;
+
+        let assignments = self
+            .statement
+            .assignments
+            .iter()
+            .map(|assignment| {
+                let column = table
+                    .columns
+                    .iter()
+                    .find(|column| column.column_name == assignment.column)
+                    .ok_or_else(|| DbErr::ColumnNotExists(assignment.column.clone()))?;
+
+                Ok(Assignment {
+                    column: assignment.column.clone(),
+                    value: prepared_statement::bind_to_column(&assignment.value, column, arguments)?,
+                })
+            })
+            .collect::<Result<Vec<Assignment>, DbErr>>()?;
+
+        Ok(Box::new(UpdateQuery {
+            statement: Update {
+                table: self.statement.table.clone(),
+                assignments,
+                selection: self.statement.selection.as_ref().map(|condition| condition.bind(arguments)),
+            },
+        }))
+    }
+}
+
+/// The values of a row the update matched, before and after
+struct RowUpdate {
+    row_id: RowId,
+    old_values: Vec<FieldValue>,
+    new_values: Vec<FieldValue>,
+}
+
+impl UpdateQuery {
+    /// The new values of every row the condition matches. Every value is computed from the rows as they
+    /// were before the update, so `SET a = b, b = a` swaps the two columns.
+    fn updated_rows(&self, table: &Table) -> Result<Vec<RowUpdate>, DbErr> {
+        let evaluator = Evaluator::new(Some(&self.statement.table.name), &table.columns);
+
+        let mut targets: Vec<usize> = Vec::new();
+        for assignment in &self.statement.assignments {
+            let idx = table
+                .columns
+                .iter()
+                .position(|column| column.column_name == assignment.column)
+                .ok_or_else(|| DbErr::ColumnNotExists(assignment.column.clone()))?;
+
+            if targets.contains(&idx) {
+                return Err(DbErr::Generic(format!("Column {} is assigned more than once", assignment.column)));
+            }
+            targets.push(idx);
+            evaluator.check_columns(&assignment.value)?;
+        }
+        if let Some(condition) = &self.statement.selection {
+            evaluator.check_columns(condition)?;
+        }
+
+        let mut updates: Vec<RowUpdate> = Vec::new();
+        for (row_id, row) in table.rows.iter() {
+            if let Some(condition) = &self.statement.selection {
+                if !evaluator.matches(condition, &row.fields)? {
+                    continue;
+                }
+            }
+
+            let mut new_values = row.fields.clone();
+            for (assignment, idx) in self.statement.assignments.iter().zip(&targets) {
+                let column = &table.columns[*idx];
+                let value = evaluator.evaluate(&assignment.value, &row.fields)?;
+                new_values[*idx] = column.column_type.coerce(value, &column.column_name)?;
+            }
+            updates.push(RowUpdate {
+                row_id,
+                old_values: row.fields.clone(),
+                new_values,
+            });
+        }
+
+        Ok(updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::Database,
+        errors::DbErr,
+        field_value::FieldValue,
+        queries::{query_parser, QuerySuccess},
+    };
+
+    fn database() -> Database {
+        let mut database = Database::new();
+        query_parser::execute_query(
+            &mut database,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a INTEGER CHECK (a < 100), b TEXT NOT NULL);
+             INSERT INTO t VALUES (1, 10, 'x'), (2, 20, 'y'), (3, NULL, 'z');",
+        )
+        .unwrap();
+        database
+    }
+
+    fn column(database: &Database, idx: usize) -> Vec<FieldValue> {
+        database
+            .get_table("t")
+            .unwrap()
+            .rows
+            .entries()
+            .map(|row| row.fields[idx].clone())
+            .collect()
+    }
+
+    #[test]
+    fn update_matching_rows() {
+        let mut database = database();
+
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(2)),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = a * 2, b = b || '!' WHERE id < 3;")
+        );
+        assert_eq!(
+            vec![FieldValue::Integer(20), FieldValue::Integer(40), FieldValue::Null],
+            column(&database, 1)
+        );
+        assert_eq!(
+            vec![
+                FieldValue::Text(String::from("x!")),
+                FieldValue::Text(String::from("y!")),
+                FieldValue::Text(String::from("z"))
+            ],
+            column(&database, 2)
+        );
+
+        // Unknown conditions match nothing
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(0)),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = 0 WHERE a > NULL;")
+        );
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(3)),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = 1.0;")
+        );
+        assert_eq!(vec![FieldValue::Integer(1); 3], column(&database, 1));
+    }
+
+    #[test]
+    fn values_come_from_the_old_row() {
+        let mut database = database();
+
+        query_parser::execute_query(&mut database, "UPDATE t SET id = a, a = id WHERE id = 1;").unwrap();
+        assert_eq!(FieldValue::Integer(10), column(&database, 0)[0]);
+        assert_eq!(FieldValue::Integer(1), column(&database, 1)[0]);
+    }
+
+    #[test]
+    fn set_names_a_column() {
+        let mut database = Database::new();
+        query_parser::execute_query(&mut database, "CREATE TABLE s (set INTEGER); INSERT INTO s VALUES (1);").unwrap();
+
+        assert_eq!(
+            Ok(QuerySuccess::RowsAffected(1)),
+            query_parser::execute_query(&mut database, "UPDATE s SET set = set + 1 WHERE set = 1;")
+        );
+        assert_eq!(
+            FieldValue::Integer(2),
+            database.get_table("s").unwrap().rows.entries().next().unwrap().fields[0]
+        );
+    }
+
+    #[test]
+    fn one_bad_row_updates_nothing() {
+        let mut database = database();
+
+        assert_eq!(
+            DbErr::CheckViolation {
+                constraint: String::from("t_a_check")
+            },
+            query_parser::execute_query(&mut database, "UPDATE t SET a = a + 85;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::PrimaryKeyViolation {
+                constraint: String::from("t_pkey")
+            },
+            query_parser::execute_query(&mut database, "UPDATE t SET id = id + 1;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::NotNullViolation { column: String::from("b") },
+            query_parser::execute_query(&mut database, "UPDATE t SET b = NULL WHERE id = 3;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TypeMismatch(String::from("Can't store TEXT in INTEGER column a")),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = b;").unwrap_err()
+        );
+        assert_eq!(
+            vec![FieldValue::Integer(1), FieldValue::Integer(2), FieldValue::Integer(3)],
+            column(&database, 0)
+        );
+        assert_eq!(
+            vec![FieldValue::Integer(10), FieldValue::Integer(20), FieldValue::Null],
+            column(&database, 1)
+        );
+
+        // The index still holds the original keys
+        assert!(query_parser::execute_query(&mut database, "INSERT INTO t VALUES (3, 0, 'w');").is_err());
+        assert!(query_parser::execute_query(&mut database, "INSERT INTO t VALUES (4, 0, 'w');").is_ok());
+    }
+
+    #[test]
+    fn invalid_updates() {
+        let mut database = database();
+
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("c")),
+            query_parser::execute_query(&mut database, "UPDATE t SET c = 1;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::ColumnNotExists(String::from("c")),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = 1 WHERE c = 1;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Generic(String::from("Column a is assigned more than once")),
+            query_parser::execute_query(&mut database, "UPDATE t SET a = 1, a = 2;").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::TableNotExists,
+            query_parser::execute_query(&mut database, "UPDATE missing SET a = 1;").unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeSet;
+
+use crate::database::TableEntry;
+
+/// Identifies a row for as long as it exists, no matter what happens to the rows around it. The slot of a
+/// deleted row is reused, but under a new generation, so an id kept after its row was deleted never finds
+/// the row that took its place. A slot that runs out of generations is retired rather than starting over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RowId {
+    slot: usize,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    entry: Option<TableEntry>,
+}
+
+/// The rows of a table, in slots that keep their position when other rows are deleted. Empty slots at the
+/// end are given back, so scanning takes time in proportion to the last row rather than the most rows the
+/// table ever held. Inserts fill the lowest empty slot first, which keeps the rows towards the front.
+pub struct RowStore {
+    slots: Vec<Slot>,
+    /// Slots whose rows were deleted, filled again by the next inserts
+    free: BTreeSet<usize>,
+    /// The generation new slots start at. It stays above the generations of every slot given back, so ids
+    /// for the rows that were in them never match the rows that come after.
+    first_generation: u32,
+    len: usize,
+}
+
+impl RowStore {
+    pub(crate) fn new() -> RowStore {
+        RowStore {
+            slots: Vec::new(),
+            free: BTreeSet::new(),
+            first_generation: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn insert(&mut self, entry: TableEntry) -> RowId {
+        self.len += 1;
+
+        match self.free.pop_first() {
+            Some(slot) => {
+                self.slots[slot].entry = Some(entry);
+                RowId {
+                    slot,
+                    generation: self.slots[slot].generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: self.first_generation,
+                    entry: Some(entry),
+                });
+                RowId {
+                    slot: self.slots.len() - 1,
+                    generation: self.first_generation,
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, row_id: RowId) -> Option<&TableEntry> {
+        self.slots
+            .get(row_id.slot)
+            .filter(|slot| slot.generation == row_id.generation)
+            .and_then(|slot| slot.entry.as_ref())
+    }
+
+    pub(crate) fn get_mut(&mut self, row_id: RowId) -> Option<&mut TableEntry> {
+        self.slots
+            .get_mut(row_id.slot)
+            .filter(|slot| slot.generation == row_id.generation)
+            .and_then(|slot| slot.entry.as_mut())
+    }
+
+    pub(crate) fn remove(&mut self, row_id: RowId) -> Option<TableEntry> {
+        let slot = self
+            .slots
+            .get_mut(row_id.slot)
+            .filter(|slot| slot.generation == row_id.generation)?;
+        let entry = slot.entry.take()?;
+
+        // Every id handed out for the slot so far is now stale. Starting the generations over would make the
+        // oldest of them valid again, so a slot that has used up its generations stays empty for good.
+        if let Some(generation) = slot.generation.checked_add(1) {
+            slot.generation = generation;
+            self.free.insert(row_id.slot);
+        }
+        self.len -= 1;
+        self.shrink();
+        Some(entry)
+    }
+
+    /// Gives back the empty slots at the end, along with the memory they took up once most of it is unused.
+    /// Retired slots stay, as a slot added in their place could only start its generations over.
+    fn shrink(&mut self) {
+        while self.slots.len().checked_sub(1).is_some_and(|last| self.free.remove(&last)) {
+            let slot = self.slots.pop().unwrap();
+            self.first_generation = self.first_generation.max(slot.generation);
+        }
+
+        if self.slots.len() < self.slots.capacity() / 4 {
+            self.slots.shrink_to(self.slots.capacity() / 2);
+        }
+    }
+
+    /// Every row with its id, in the order of their slots
+    pub fn iter(&self) -> impl Iterator<Item = (RowId, &TableEntry)> {
+        self.slots.iter().enumerate().filter_map(|(idx, slot)| {
+            slot.entry.as_ref().map(|entry| {
+                let row_id = RowId {
+                    slot: idx,
+                    generation: slot.generation,
+                };
+                (row_id, entry)
+            })
+        })
+    }
+
+    /// Every row, in the order of their slots
+    pub fn entries(&self) -> impl Iterator<Item = &TableEntry> {
+        self.iter().map(|(_, entry)| entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::TableEntry,
+        field_value::FieldValue,
+        row_store::{RowId, RowStore},
+    };
+
+    fn entry(value: i64) -> TableEntry {
+        TableEntry {
+            fields: vec![FieldValue::Integer(value)],
+        }
+    }
+
+    fn values(store: &RowStore) -> Vec<FieldValue> {
+        store.entries().map(|entry| entry.fields[0].clone()).collect()
+    }
+
+    #[test]
+    fn ids_survive_deletes() {
+        let mut store = RowStore::new();
+        let first = store.insert(entry(1));
+        let second = store.insert(entry(2));
+        let third = store.insert(entry(3));
+
+        assert!(store.remove(second).is_some());
+        assert_eq!(2, store.len());
+        assert_eq!(FieldValue::Integer(1), store.get(first).unwrap().fields[0]);
+        assert_eq!(FieldValue::Integer(3), store.get(third).unwrap().fields[0]);
+        assert!(store.get(second).is_none());
+        assert!(store.remove(second).is_none());
+    }
+
+    #[test]
+    fn freed_slots_are_reused_under_new_ids() {
+        let mut store = RowStore::new();
+        store.insert(entry(1));
+        let deleted = store.insert(entry(2));
+        store.insert(entry(3));
+
+        store.remove(deleted);
+        let reused = store.insert(entry(4));
+
+        assert_ne!(deleted, reused);
+        assert!(store.get(deleted).is_none());
+        assert_eq!(FieldValue::Integer(4), store.get(reused).unwrap().fields[0]);
+        // The new row fills the hole rather than growing the table
+        assert_eq!(
+            vec![FieldValue::Integer(1), FieldValue::Integer(4), FieldValue::Integer(3)],
+            values(&store)
+        );
+    }
+
+    #[test]
+    fn exhausted_slots_are_retired() {
+        let mut store = RowStore::new();
+        store.insert(entry(1));
+        store.slots[0].generation = u32::MAX;
+        let last = RowId {
+            slot: 0,
+            generation: u32::MAX,
+        };
+
+        assert!(store.remove(last).is_some());
+        let next = store.insert(entry(2));
+        assert_eq!(1, next.slot);
+        assert!(store.get(last).is_none());
+        assert!(store.get(RowId { slot: 0, generation: 0 }).is_none());
+        assert_eq!(vec![FieldValue::Integer(2)], values(&store));
+    }
+
+    #[test]
+    fn deleting_rows_gives_back_their_slots() {
+        let mut store = RowStore::new();
+        let row_ids: Vec<RowId> = (0..1000).map(|value| store.insert(entry(value))).collect();
+        let capacity = store.slots.capacity();
+
+        // Delete all but the first ten rows, the later ones first so every delete leaves the end empty
+        for row_id in row_ids[10..].iter().rev() {
+            store.remove(*row_id);
+        }
+        assert_eq!(10, store.len());
+        assert_eq!(10, store.slots.len());
+        assert!(store.slots.capacity() < capacity / 4);
+
+        // Holes in the middle are given back once the rows after them are gone
+        for row_id in &row_ids[2..8] {
+            store.remove(*row_id);
+        }
+        assert_eq!(10, store.slots.len());
+        store.remove(row_ids[9]);
+        store.remove(row_ids[8]);
+        assert_eq!(2, store.slots.len());
+        assert!(store.free.is_empty());
+        assert_eq!(vec![FieldValue::Integer(0), FieldValue::Integer(1)], values(&store));
+
+        // Slots added again don't let the ids of the rows that were in them match the new rows
+        let added = store.insert(entry(5));
+        assert_eq!(row_ids[2].slot, added.slot);
+        assert_ne!(row_ids[2], added);
+        assert!(row_ids[2..].iter().all(|row_id| store.get(*row_id).is_none()));
+        assert_eq!(FieldValue::Integer(5), store.get(added).unwrap().fields[0]);
+    }
+
+    #[test]
+    fn update_in_place() {
+        let mut store = RowStore::new();
+        let row_id = store.insert(entry(1));
+
+        store.get_mut(row_id).unwrap().set_field(0, FieldValue::Integer(5));
+        assert_eq!(vec![FieldValue::Integer(5)], values(&store));
+        assert!(!store.is_empty());
+    }
+}
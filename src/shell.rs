@@ -0,0 +1,158 @@
+use crate::{database::Database, errors::DbErr, queries::query_parser::execute_script};
+
+/// An interactive session. Input is collected line by line and run once it ends with a `;` outside of any
+/// string or comment, apart from `HELP [statement]`, which shows the syntax of the registered statements
+/// straight away.
+pub struct Shell {
+    database: Database,
+    buffer: String,
+}
+
+impl Shell {
+    pub fn new(database: Database) -> Shell {
+        Shell {
+            database,
+            buffer: String::new(),
+        }
+    }
+
+    /// Whether the input so far is an unfinished statement
+    pub fn is_continuing(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Handles one line of input, returning what should be printed in response
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if !self.is_continuing() {
+            if let Some(statement) = help_argument(line) {
+                return Some(match self.database.syntax_help(statement) {
+                    Ok(syntax) => syntax,
+                    Err(err) => error_message(&err, line),
+                });
+            }
+        }
+
+        if line.trim().is_empty() && !self.is_continuing() {
+            return None;
+        }
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if !line.trim_end().ends_with(';') || !self.database.registry().tokenizer().ends_statement(&self.buffer) {
+            return None;
+        }
+
+        let query = std::mem::take(&mut self.buffer);
+        let output: Vec<String> = execute_script(&mut self.database, &query)
+            .results
+            .into_iter()
+            .map(|result| match result {
+                Ok(success) => success.to_string(),
+                Err(err) => error_message(&err, &query),
+            })
+            .collect();
+
+        Some(output.join("\n"))
+    }
+}
+
+/// The statement a `HELP` line asks about, if it is one
+fn help_argument(line: &str) -> Option<&str> {
+    let line = line.trim().trim_end_matches(';');
+    let keyword = line.get(..4)?;
+    let rest = &line[4..];
+
+    if keyword.eq_ignore_ascii_case("HELP") && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn error_message(err: &DbErr, query: &str) -> String {
+    match err {
+        DbErr::Syntax(err) => err.render(query),
+        DbErr::Generic(message)
+        | DbErr::InvalidDataType(message)
+        | DbErr::TypeMismatch(message)
+        | DbErr::ArithmeticError(message)
+        | DbErr::InvalidConstraint(message) => message.clone(),
+        DbErr::ColumnNotExists(column) => format!("Column {} doesn't exist", column),
+        DbErr::NotNullViolation { column } => format!("Column {} can't be NULL", column),
+        DbErr::UniqueViolation { constraint } | DbErr::PrimaryKeyViolation { constraint } => {
+            format!("Duplicate key violates constraint {}", constraint)
+        }
+        DbErr::CheckViolation { constraint } => format!("Row violates check constraint {}", constraint),
+        err => format!("{:?}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{database::Database, field_value::FieldValue, shell::Shell};
+
+    #[test]
+    fn statements_run_once_complete() {
+        let mut shell = Shell::new(Database::new());
+
+        assert_eq!(None, shell.handle_line("CREATE TABLE users"));
+        assert!(shell.is_continuing());
+        assert_eq!(Some(String::from("Done")), shell.handle_line("(name string);"));
+        assert!(!shell.is_continuing());
+        assert_eq!(
+            Some(String::from("TableAlreadyExists")),
+            shell.handle_line("CREATE TABLE users (name string);")
+        );
+        assert_eq!(None, shell.handle_line(""));
+    }
+
+    #[test]
+    fn strings_and_comments_span_lines() {
+        let mut shell = Shell::new(Database::new());
+        shell.handle_line("CREATE TABLE t (a TEXT);");
+
+        assert_eq!(None, shell.handle_line("INSERT INTO t VALUES ('a;"));
+        assert_eq!(Some(String::from("1 row affected")), shell.handle_line("b');"));
+        assert_eq!(None, shell.handle_line("SELECT a /* first;"));
+        assert_eq!(None, shell.handle_line("*/ FROM t -- all rows;"));
+        assert!(shell.handle_line("WHERE a <> '';").unwrap().ends_with("(1 row)"));
+
+        let table = shell.database.get_table("t").unwrap();
+        assert_eq!(
+            FieldValue::Text(String::from("a;\nb")),
+            table.rows.entries().next().unwrap().fields[0]
+        );
+    }
+
+    #[test]
+    fn query_results_are_tables() {
+        let mut shell = Shell::new(Database::new());
+
+        shell.handle_line("CREATE TABLE t (a INTEGER, b TEXT); INSERT INTO t VALUES (1, 'one'), (2, NULL);");
+        assert_eq!(
+            Some(String::from("a | b\n--+-----\n2 | NULL\n(1 row)")),
+            shell.handle_line("SELECT * FROM t WHERE a > 1;")
+        );
+        assert_eq!(Some(String::from("2 rows affected")), shell.handle_line("UPDATE t SET a = a + 1;"));
+    }
+
+    #[test]
+    fn help() {
+        let mut shell = Shell::new(Database::new());
+
+        assert_eq!(
+            Some(String::from(
+                "delete_table = ( \"DELETE\" | \"DROP\" ) \"TABLE\" [ \"IF\" \"EXISTS\" ] table_name \";\" ;"
+            )),
+            shell.handle_line("help delete table;")
+        );
+        let everything = shell.handle_line("HELP\n").unwrap();
+        assert!(everything.starts_with("create_table = "));
+        assert!(everything.contains("\n\ndelete_table = "));
+        assert!(shell.handle_line("HELP ME").unwrap().starts_with("Unknown statement ME"));
+        // Inside a statement, HELP is just another identifier
+        assert_eq!(None, shell.handle_line("DELETE TABLE"));
+        assert_eq!(Some(String::from("TableNotExists")), shell.handle_line("help;"));
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use super::{Token, TokenParser};
+use crate::errors::DbErr;
 use regex::Regex;
 
 pub(crate) struct IdentifierParser {
@@ -17,26 +18,23 @@ impl IdentifierParser {
     }
 
     pub(crate) fn add_token_mapping(&mut self, identifier: String, token_type: Token) {
-        self.token_mapping
-            .insert(identifier.to_lowercase(), token_type);
+        self.token_mapping.insert(identifier.to_lowercase(), token_type);
     }
 }
 
 impl TokenParser for IdentifierParser {
-    fn parse_skip(&self, input: &mut String) -> Option<super::Token> {
-        match self.regex.find(&input.clone()) {
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr> {
+        match self.regex.find(input) {
             Some(result) => {
-                input.replace_range(0..result.len(), "");
-
                 // Check for a mapped identifier, and return that if we can
                 let lower_result = result.as_str().to_lowercase();
 
                 match self.token_mapping.get(&lower_result) {
-                    Some(mapping) => Some(mapping.clone()),
-                    None => Some(Token::Identifier(result.as_str().to_owned())),
+                    Some(mapping) => Ok(Some((mapping.clone(), result.end()))),
+                    None => Ok(Some((Token::Identifier(result.as_str().to_owned()), result.end()))),
                 }
             }
-            None => None,
+            None => Ok(None),
         }
     }
 }
@@ -45,34 +43,35 @@ impl TokenParser for IdentifierParser {
 mod tests {
     use crate::tokenizer::{identifier_parser::IdentifierParser, Token, TokenParser};
 
+    fn identifier(name: &str) -> Token {
+        Token::Identifier(String::from(name))
+    }
+
     #[test]
     fn string_identifier() {
-        let token = IdentifierParser::new().parse_skip(&mut String::from("MYSTRINGIDENTIFIER"));
+        let token = IdentifierParser::new().parse("MYSTRINGIDENTIFIER").unwrap();
 
-        assert_eq!(
-            Token::Identifier(String::from("MYSTRINGIDENTIFIER")),
-            token.unwrap()
-        );
+        assert_eq!((identifier("MYSTRINGIDENTIFIER"), 18), token.unwrap());
     }
 
     #[test]
     fn identifiers_with_underscores() {
-        let token1 = IdentifierParser::new().parse_skip(&mut String::from("my_str"));
-        let token2 = IdentifierParser::new().parse_skip(&mut String::from("_egg"));
-        let token3 = IdentifierParser::new().parse_skip(&mut String::from("_"));
+        let token1 = IdentifierParser::new().parse("my_str").unwrap();
+        let token2 = IdentifierParser::new().parse("_egg").unwrap();
+        let token3 = IdentifierParser::new().parse("_").unwrap();
 
-        assert_eq!(Token::Identifier(String::from("my_str")), token1.unwrap());
-        assert_eq!(Token::Identifier(String::from("_egg")), token2.unwrap());
-        assert_eq!(Token::Identifier(String::from("_")), token3.unwrap());
+        assert_eq!((identifier("my_str"), 6), token1.unwrap());
+        assert_eq!((identifier("_egg"), 4), token2.unwrap());
+        assert_eq!((identifier("_"), 1), token3.unwrap());
     }
 
     #[test]
     fn identifiers_with_numbers() {
-        let token1 = IdentifierParser::new().parse_skip(&mut String::from("hi1"));
-        let token2 = IdentifierParser::new().parse_skip(&mut String::from("h_32"));
+        let token1 = IdentifierParser::new().parse("hi1").unwrap();
+        let token2 = IdentifierParser::new().parse("h_32").unwrap();
 
-        assert_eq!(Token::Identifier(String::from("hi1")), token1.unwrap());
-        assert_eq!(Token::Identifier(String::from("h_32")), token2.unwrap());
+        assert_eq!((identifier("hi1"), 3), token1.unwrap());
+        assert_eq!((identifier("h_32"), 4), token2.unwrap());
     }
 
     #[test]
@@ -81,21 +80,21 @@ mod tests {
         parser.add_token_mapping(String::from("Create"), Token::Create);
         parser.add_token_mapping(String::from("TABLE"), Token::Table);
 
-        let create1 = parser.parse_skip(&mut String::from("CREATE")).unwrap();
-        let create2 = parser.parse_skip(&mut String::from("CREATE EGGS")).unwrap();
-        let table1 = parser.parse_skip(&mut String::from("TABLE EGGS")).unwrap();
-        let table2 = parser.parse_skip(&mut String::from("table EGGS")).unwrap();
+        let create1 = parser.parse("CREATE").unwrap().unwrap();
+        let create2 = parser.parse("CREATE EGGS").unwrap().unwrap();
+        let table1 = parser.parse("TABLE EGGS").unwrap().unwrap();
+        let table2 = parser.parse("table EGGS").unwrap().unwrap();
 
-        assert_eq!(Token::Create, create1);
-        assert_eq!(Token::Create, create2);
-        assert_eq!(Token::Table, table1);
-        assert_eq!(Token::Table, table2);
+        assert_eq!((Token::Create, 6), create1);
+        assert_eq!((Token::Create, 6), create2);
+        assert_eq!((Token::Table, 5), table1);
+        assert_eq!((Token::Table, 5), table2);
     }
 
     #[test]
     fn no_matches() {
-        let empty1 = IdentifierParser::new().parse_skip(&mut String::from("123_hello"));
-        let empty2 = IdentifierParser::new().parse_skip(&mut String::from("+932"));
+        let empty1 = IdentifierParser::new().parse("123_hello").unwrap();
+        let empty2 = IdentifierParser::new().parse("+932").unwrap();
 
         assert_eq!(None, empty1);
         assert_eq!(None, empty2);
@@ -106,9 +105,6 @@ mod tests {
         let mut parser = IdentifierParser::new();
         parser.add_token_mapping(String::from("Table"), Token::Table);
 
-        assert_eq!(
-            Token::Identifier(String::from("Table_")),
-            parser.parse_skip(&mut String::from("Table_")).unwrap()
-        );
+        assert_eq!((identifier("Table_"), 6), parser.parse("Table_").unwrap().unwrap());
     }
 }
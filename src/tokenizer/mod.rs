@@ -1,79 +1,504 @@
-use crate::errors::DbErr;
+use crate::errors::{DbErr, SyntaxError};
 
-use self::{identifier_parser::IdentifierParser, simple_parser::SimpleParser};
+use self::{
+    identifier_parser::IdentifierParser, quoted_identifier_parser::QuotedIdentifierParser, regex_parser::RegexParser,
+    simple_parser::SimpleParser, string_parser::StringParser,
+};
 
 mod identifier_parser;
+mod quoted_identifier_parser;
 mod regex_parser;
 mod simple_parser;
+mod string_parser;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-struct ErrorInfo;
+/// The location of a token in the query text. `start` and `end` are byte offsets,
+/// `line` and `column` are 1-based and point at the first character of the token.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// An empty span just past the final character of the query, used when the query ends too early
+    pub(crate) fn end_of(query_string: &str) -> Span {
+        let mut location = Location::new();
+        location.advance_to(query_string, query_string.len());
+        location.span_to(query_string.len())
+    }
+
+    /// The first `len` bytes of a token parser's input, for errors about the text it matched. The tokenizer
+    /// moves the span to where that input starts in the query.
+    pub(crate) fn leading(len: usize) -> Span {
+        Span {
+            start: 0,
+            end: len,
+            line: 1,
+            column: 1,
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Token {
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// A bind parameter in a prepared statement
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Placeholder {
+    /// `?`, numbered by its position among the other `?` placeholders
+    Anonymous,
+    /// `$1`, `$2`, ...
+    Numbered(usize),
+    /// `:name`
+    Named(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
     Create,
     Delete,
     Table,
+    Drop,
+    If,
+    Exists,
+    Not,
+    And,
+    Or,
+    Is,
+    Null,
+    Between,
+    In,
+    True,
+    False,
+    Primary,
+    Constraint,
+    Insert,
+    Into,
+    Values,
+    Select,
+    From,
+    Where,
+    As,
+    Update,
+    Order,
+    By,
+    Asc,
+    Desc,
+    Limit,
+    Offset,
+    Fetch,
     Comma,
     OpenBracket,
     CloseBracket,
     SemiColon,
+    Dot,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    Plus,
+    Minus,
+    Asterisk,
+    Slash,
+    Percent,
+    Concat,
     Identifier(String),
-    List(Vec<Token>),
+    // Numeric literals are always unsigned, a leading `-` or `+` is left to the parser
+    Integer(i64),
+    Decimal(f64),
+    Scientific(f64),
+    StringLiteral(String),
+    /// `X'0AFF'`
+    BlobLiteral(Vec<u8>),
+    Placeholder(Placeholder),
+}
+
+impl Token {
+    /// The text of tokens that are always written the same way, such as keywords and punctuation
+    pub(crate) fn text(&self) -> Option<&'static str> {
+        let text = match self {
+            Token::Create => "CREATE",
+            Token::Delete => "DELETE",
+            Token::Table => "TABLE",
+            Token::Drop => "DROP",
+            Token::If => "IF",
+            Token::Exists => "EXISTS",
+            Token::Not => "NOT",
+            Token::And => "AND",
+            Token::Or => "OR",
+            Token::Is => "IS",
+            Token::Null => "NULL",
+            Token::Between => "BETWEEN",
+            Token::In => "IN",
+            Token::True => "TRUE",
+            Token::False => "FALSE",
+            Token::Primary => "PRIMARY",
+            Token::Constraint => "CONSTRAINT",
+            Token::Insert => "INSERT",
+            Token::Into => "INTO",
+            Token::Values => "VALUES",
+            Token::Select => "SELECT",
+            Token::From => "FROM",
+            Token::Where => "WHERE",
+            Token::As => "AS",
+            Token::Update => "UPDATE",
+            Token::Order => "ORDER",
+            Token::By => "BY",
+            Token::Asc => "ASC",
+            Token::Desc => "DESC",
+            Token::Limit => "LIMIT",
+            Token::Offset => "OFFSET",
+            Token::Fetch => "FETCH",
+            Token::Comma => ",",
+            Token::OpenBracket => "(",
+            Token::CloseBracket => ")",
+            Token::SemiColon => ";",
+            Token::Dot => ".",
+            Token::Equals => "=",
+            Token::NotEquals => "<>",
+            Token::LessThan => "<",
+            Token::LessOrEqual => "<=",
+            Token::GreaterThan => ">",
+            Token::GreaterOrEqual => ">=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Asterisk => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Concat => "||",
+            Token::Identifier(_)
+            | Token::Integer(_)
+            | Token::Decimal(_)
+            | Token::Scientific(_)
+            | Token::StringLiteral(_)
+            | Token::BlobLiteral(_)
+            | Token::Placeholder(_) => return None,
+        };
+
+        Some(text)
+    }
+
+    /// Describes the kind of token for error messages, ignoring any value it carries
+    pub(crate) fn describe(&self) -> String {
+        if let Some(text) = self.text() {
+            return format!("`{}`", text);
+        }
+
+        String::from(match self {
+            Token::Integer(_) => "integer",
+            Token::Decimal(_) | Token::Scientific(_) => "number",
+            Token::StringLiteral(_) => "string",
+            Token::BlobLiteral(_) => "blob",
+            Token::Placeholder(_) => "parameter",
+            Token::Identifier(_) => "identifier",
+            _ => unreachable!("tokens with a fixed text are described by it"),
+        })
+    }
 }
 
 pub trait TokenParser {
-    fn parse_skip(&self, input: &mut String) -> Option<Token>;
+    /// Attempts to read a token from the start of `input`. On a match, returns the token along with
+    /// the number of bytes it spans. A `DbErr::Syntax` error rejects the text under its span, which is
+    /// relative to `input`, and is only reported if no other parser reads further.
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr>;
 }
 
 pub struct Tokenizer {
     parsers: Vec<Box<dyn TokenParser>>,
 }
 
+/// Tracks the line and column while walking forward through the query
+#[derive(Copy, Clone)]
+struct Location {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn new() -> Location {
+        Location {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn advance_to(&mut self, query_string: &str, offset: usize) {
+        for c in query_string[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.offset = offset;
+    }
+
+    fn span_to(&self, end: usize) -> Span {
+        Span {
+            start: self.offset,
+            end,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// A syntax error underlining the character at this location
+    fn error(&self, query_string: &str, message: String) -> DbErr {
+        let width = query_string[self.offset..].chars().next().map_or(0, char::len_utf8);
+        DbErr::Syntax(SyntaxError::new(message, self.span_to(self.offset + width)))
+    }
+}
+
 impl Tokenizer {
     pub(crate) fn new() -> Tokenizer {
         let mut identifier_parser = IdentifierParser::new();
         identifier_parser.add_token_mapping(String::from("create"), Token::Create);
         identifier_parser.add_token_mapping(String::from("table"), Token::Table);
         identifier_parser.add_token_mapping(String::from("delete"), Token::Delete);
+        identifier_parser.add_token_mapping(String::from("drop"), Token::Drop);
+        identifier_parser.add_token_mapping(String::from("if"), Token::If);
+        identifier_parser.add_token_mapping(String::from("exists"), Token::Exists);
+        identifier_parser.add_token_mapping(String::from("not"), Token::Not);
+        identifier_parser.add_token_mapping(String::from("and"), Token::And);
+        identifier_parser.add_token_mapping(String::from("or"), Token::Or);
+        identifier_parser.add_token_mapping(String::from("is"), Token::Is);
+        identifier_parser.add_token_mapping(String::from("null"), Token::Null);
+        identifier_parser.add_token_mapping(String::from("between"), Token::Between);
+        identifier_parser.add_token_mapping(String::from("in"), Token::In);
+        identifier_parser.add_token_mapping(String::from("true"), Token::True);
+        identifier_parser.add_token_mapping(String::from("false"), Token::False);
+        identifier_parser.add_token_mapping(String::from("primary"), Token::Primary);
+        identifier_parser.add_token_mapping(String::from("constraint"), Token::Constraint);
+        identifier_parser.add_token_mapping(String::from("insert"), Token::Insert);
+        identifier_parser.add_token_mapping(String::from("into"), Token::Into);
+        identifier_parser.add_token_mapping(String::from("values"), Token::Values);
+        identifier_parser.add_token_mapping(String::from("select"), Token::Select);
+        identifier_parser.add_token_mapping(String::from("from"), Token::From);
+        identifier_parser.add_token_mapping(String::from("where"), Token::Where);
+        identifier_parser.add_token_mapping(String::from("as"), Token::As);
+        identifier_parser.add_token_mapping(String::from("update"), Token::Update);
+        identifier_parser.add_token_mapping(String::from("order"), Token::Order);
+        identifier_parser.add_token_mapping(String::from("by"), Token::By);
+        identifier_parser.add_token_mapping(String::from("asc"), Token::Asc);
+        identifier_parser.add_token_mapping(String::from("desc"), Token::Desc);
+        identifier_parser.add_token_mapping(String::from("limit"), Token::Limit);
+        identifier_parser.add_token_mapping(String::from("offset"), Token::Offset);
+        identifier_parser.add_token_mapping(String::from("fetch"), Token::Fetch);
 
         Tokenizer {
             parsers: vec![
+                Box::from(StringParser::new()),
+                Box::from(QuotedIdentifierParser::new()),
                 Box::from(identifier_parser),
+                Box::from(RegexParser::blob()),
+                Box::from(RegexParser::hex_integer()),
+                Box::from(RegexParser::scientific()),
+                Box::from(RegexParser::decimal()),
+                Box::from(RegexParser::integer()),
                 Box::from(SimpleParser::new(String::from(","), Token::Comma)),
                 Box::from(SimpleParser::new(String::from("("), Token::OpenBracket)),
                 Box::from(SimpleParser::new(String::from(")"), Token::CloseBracket)),
                 Box::from(SimpleParser::new(String::from(";"), Token::SemiColon)),
+                Box::from(SimpleParser::new(String::from("."), Token::Dot)),
+                Box::from(SimpleParser::new(String::from("="), Token::Equals)),
+                Box::from(SimpleParser::new(String::from("<>"), Token::NotEquals)),
+                Box::from(SimpleParser::new(String::from("!="), Token::NotEquals)),
+                Box::from(SimpleParser::new(String::from("<"), Token::LessThan)),
+                Box::from(SimpleParser::new(String::from("<="), Token::LessOrEqual)),
+                Box::from(SimpleParser::new(String::from(">"), Token::GreaterThan)),
+                Box::from(SimpleParser::new(String::from(">="), Token::GreaterOrEqual)),
+                Box::from(SimpleParser::new(String::from("+"), Token::Plus)),
+                Box::from(SimpleParser::new(String::from("-"), Token::Minus)),
+                Box::from(SimpleParser::new(String::from("*"), Token::Asterisk)),
+                Box::from(SimpleParser::new(String::from("/"), Token::Slash)),
+                Box::from(SimpleParser::new(String::from("%"), Token::Percent)),
+                Box::from(SimpleParser::new(String::from("||"), Token::Concat)),
+                Box::from(SimpleParser::new(String::from("?"), Token::Placeholder(Placeholder::Anonymous))),
+                Box::from(RegexParser::numbered_placeholder()),
+                Box::from(RegexParser::named_placeholder()),
             ],
         }
     }
 
     pub(crate) fn tokenize(&self, query_string: &str) -> Result<Vec<Token>, DbErr> {
-        let mut tokens: Vec<Token> = Vec::new();
+        self.tokens(query_string)
+            .map(|spanned| spanned.map(|spanned| spanned.token))
+            .collect()
+    }
+
+    pub(crate) fn tokenize_spanned(&self, query_string: &str) -> Result<Vec<SpannedToken>, DbErr> {
+        self.tokens(query_string).collect()
+    }
+
+    /// Whether the text ends with a `;` that finishes a statement, and not one inside a string, quoted identifier
+    /// or block comment that is still open. Text that fails to tokenize for any other reason is finished once it
+    /// ends with `;`, so the error gets reported.
+    pub(crate) fn ends_statement(&self, text: &str) -> bool {
+        let mut last: Option<Token> = None;
+        for spanned in self.tokens(text) {
+            match spanned {
+                Ok(spanned) => last = Some(spanned.token),
+                // Only the end of the text reveals that a string or comment was never closed
+                Err(DbErr::Syntax(err)) if err.incomplete => return false,
+                Err(_) => return text.trim_end().ends_with(';'),
+            }
+        }
+
+        last == Some(Token::SemiColon)
+    }
+
+    /// Lazily reads tokens from the query. The iterator stops after the first error.
+    pub(crate) fn tokens<'a>(&'a self, query_string: &'a str) -> Tokens<'a> {
+        Tokens {
+            tokenizer: self,
+            query_string,
+            location: Location::new(),
+            failed: false,
+        }
+    }
+}
+
+/// A cursor over the query text, yielding one token per call to `next`
+pub(crate) struct Tokens<'a> {
+    tokenizer: &'a Tokenizer,
+    query_string: &'a str,
+    location: Location,
+    failed: bool,
+}
 
-        let mut skippable_string = query_string.trim().to_string();
-        while !skippable_string.is_empty() {
-            let mut success = false;
+impl Tokens<'_> {
+    fn remaining(&self) -> &str {
+        &self.query_string[self.location.offset..]
+    }
+
+    fn next_token(&mut self) -> Result<Option<SpannedToken>, DbErr> {
+        self.skip_ignored()?;
+
+        let remaining = self.remaining();
+        if remaining.is_empty() {
+            return Ok(None);
+        }
 
-            for parser in &self.parsers {
-                if let Some(result) = parser.parse_skip(&mut skippable_string) {
-                    tokens.push(result);
-                    success = true;
-                    skippable_string = skippable_string.trim_start().to_string();
+        // Every parser gets a go, and the longest match wins so `<=` is never read as `<` then `=`.
+        // On a tie, the parser registered first wins, which lets keywords take priority over other tokens.
+        let mut longest: Option<(Result<Token, DbErr>, usize)> = None;
+        for parser in &self.tokenizer.parsers {
+            let parsed = match parser.parse(remaining) {
+                Ok(parsed) => parsed.map(|(token, len)| (Ok(token), len)),
+                Err(DbErr::Syntax(err)) => {
+                    let span = self.location.span_to(self.location.offset + err.span.end);
+                    Some((Err(DbErr::Syntax(SyntaxError { span, ..err })), err.span.end))
+                }
+                Err(DbErr::Generic(message)) => return Err(self.location.error(self.query_string, message)),
+                Err(err) => return Err(err),
+            };
+
+            if let Some((token, len)) = parsed {
+                if longest.as_ref().is_none_or(|(_, longest_len)| len > *longest_len) {
+                    longest = Some((token, len));
                 }
             }
+        }
+
+        match longest {
+            Some((token, len)) => {
+                let token = token?;
+                let end = self.location.offset + len;
+                let span = self.location.span_to(end);
+                self.location.advance_to(self.query_string, end);
+
+                Ok(Some(SpannedToken { token, span }))
+            }
+            None => Err(self.location.error(self.query_string, String::from("Unable to parse"))),
+        }
+    }
+
+    /// Skips leading whitespace, `-- line comments` and `/* block comments */`.
+    /// Block comments may be nested, and must be closed before the end of the query.
+    fn skip_ignored(&mut self) -> Result<(), DbErr> {
+        let bytes = self.remaining().as_bytes();
+        let mut pos = 0;
 
-            if !success {
-                return Err(DbErr::Generic(String::from("Unable to parse")));
+        loop {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
             }
+
+            if bytes[pos..].starts_with(b"--") {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else if bytes[pos..].starts_with(b"/*") {
+                let comment_start = pos;
+                let mut depth = 0;
+
+                loop {
+                    if bytes[pos..].starts_with(b"/*") {
+                        depth += 1;
+                        pos += 2;
+                    } else if bytes[pos..].starts_with(b"*/") {
+                        depth -= 1;
+                        pos += 2;
+
+                        if depth == 0 {
+                            break;
+                        }
+                    } else if pos < bytes.len() {
+                        pos += 1;
+                    } else {
+                        let comment_offset = self.location.offset + comment_start;
+                        self.location.advance_to(self.query_string, comment_offset);
+                        return Err(DbErr::Syntax(
+                            SyntaxError::new(
+                                String::from("Unterminated block comment"),
+                                self.location.span_to(comment_offset + 2),
+                            )
+                            .incomplete(),
+                        ));
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+
+        let offset = self.location.offset + pos;
+        self.location.advance_to(self.query_string, offset);
+        Ok(())
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<SpannedToken, DbErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
         }
 
-        Ok(tokens)
+        let result = self.next_token();
+        self.failed = result.is_err();
+        result.transpose()
     }
 }
+
 #[cfg(test)]
 mod tests {
-    use crate::tokenizer::{Token, Tokenizer};
+    use crate::errors::DbErr;
+    use std::time::Instant;
+
+    use crate::tokenizer::{Placeholder, Span, Token, Tokenizer};
 
     #[test]
     pub fn test_create_table() {
@@ -89,5 +514,406 @@ mod tests {
         let tokens = tokenizer.tokenize("delete DELETE TABLE").unwrap();
 
         assert_eq!(vec![Token::Delete, Token::Delete, Token::Table], tokens);
+        assert_eq!(
+            vec![Token::Drop, Token::Table, Token::If, Token::Exists],
+            tokenizer.tokenize("drop table IF exists").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_numeric_literals() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("42, 3.5, 1e10, 0x1F, .5").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Integer(42),
+                Token::Comma,
+                Token::Decimal(3.5),
+                Token::Comma,
+                Token::Scientific(1e10),
+                Token::Comma,
+                Token::Integer(31),
+                Token::Comma,
+                Token::Decimal(0.5)
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_numbers_in_query() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("(age 12)").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::OpenBracket,
+                Token::Identifier(String::from("age")),
+                Token::Integer(12),
+                Token::CloseBracket
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_string_literals() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("('it''s', E'a\\nb', 'multi\nline')").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::OpenBracket,
+                Token::StringLiteral(String::from("it's")),
+                Token::Comma,
+                Token::StringLiteral(String::from("a\nb")),
+                Token::Comma,
+                Token::StringLiteral(String::from("multi\nline")),
+                Token::CloseBracket
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_quoted_identifiers() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("CREATE TABLE \"Table\" (`Order Date`)").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Create,
+                Token::Table,
+                Token::Identifier(String::from("Table")),
+                Token::OpenBracket,
+                Token::Identifier(String::from("Order Date")),
+                Token::CloseBracket
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_comments() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer
+            .tokenize("-- leading comment\nCREATE /* inline */ TABLE -- trailing\n/* multi\nline */ users; -- end")
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Create,
+                Token::Table,
+                Token::Identifier(String::from("users")),
+                Token::SemiColon
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_nested_block_comments() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("CREATE /* outer /* inner */ still outer */ TABLE").unwrap();
+
+        assert_eq!(vec![Token::Create, Token::Table], tokens);
+        assert_eq!(Vec::<Token>::new(), tokenizer.tokenize("/* only */ -- comments").unwrap());
+    }
+
+    #[test]
+    pub fn test_comment_markers_in_literals() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("'-- not a comment' \"/* nor this */\"").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::StringLiteral(String::from("-- not a comment")),
+                Token::Identifier(String::from("/* nor this */"))
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_unterminated_block_comment() {
+        let tokenizer = Tokenizer::new();
+        let query = "CREATE\nTABLE /* outer /* inner */ users";
+
+        match tokenizer.tokenize(query).unwrap_err() {
+            DbErr::Syntax(err) => assert_eq!(
+                "Unterminated block comment at 2:7\nTABLE /* outer /* inner */ users\n      ^^",
+                err.render(query)
+            ),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    pub fn test_unterminated_string_literal() {
+        let tokenizer = Tokenizer::new();
+
+        match tokenizer.tokenize("CREATE TABLE 'users").unwrap_err() {
+            DbErr::Syntax(err) => assert_eq!("Unterminated string literal at 1:14", err.to_string()),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    pub fn test_ends_statement() {
+        let tokenizer = Tokenizer::new();
+
+        assert!(tokenizer.ends_statement("SELECT 'a;\nb';\n"));
+        assert!(tokenizer.ends_statement("SELECT 1; /* note */"));
+        assert!(!tokenizer.ends_statement("SELECT 'a;\n"));
+        assert!(!tokenizer.ends_statement("SELECT \"a;\n"));
+        assert!(!tokenizer.ends_statement("SELECT 1 /* a;\n"));
+        assert!(!tokenizer.ends_statement("SELECT 1 -- a;\n"));
+        assert!(!tokenizer.ends_statement("SELECT E'a\\';\n"));
+        assert!(!tokenizer.ends_statement("SELECT 1\n"));
+        // Any other error is reported once the line ends with `;`
+        assert!(tokenizer.ends_statement("SELECT #;\n"));
+        assert!(tokenizer.ends_statement("SELECT \"\";\n"));
+        assert!(!tokenizer.ends_statement("SELECT #\n"));
+    }
+
+    #[test]
+    pub fn test_unknown_character() {
+        let tokenizer = Tokenizer::new();
+        let query = "CREATE TABLE users\n  (name string) #;";
+
+        match tokenizer.tokenize(query).unwrap_err() {
+            DbErr::Syntax(err) => {
+                assert_eq!("Unable to parse at 2:17\n  (name string) #;\n                ^", err.render(query))
+            }
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    pub fn test_integer_out_of_range() {
+        let tokenizer = Tokenizer::new();
+        let query = "SELECT 1, 99999999999999999999 FROM t;";
+
+        match tokenizer.tokenize(query).unwrap_err() {
+            DbErr::Syntax(err) => assert_eq!(
+                "Integer literal out of range at 1:11\nSELECT 1, 99999999999999999999 FROM t;\n          ^^^^^^^^^^^^^^^^^^^^",
+                err.render(query)
+            ),
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+        assert!(tokenizer.tokenize("0x10000000000000000").is_err());
+
+        // Longer literals that don't overflow still win
+        assert_eq!(
+            Ok(vec![Token::Decimal(1e20), Token::Scientific(1e21)]),
+            tokenizer.tokenize("100000000000000000000.0 100000000000000000000e1")
+        );
+    }
+
+    #[test]
+    pub fn test_token_spans() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize_spanned("CREATE /* é */ TABLE\n  'multi\nline' x").unwrap();
+
+        let spans: Vec<Span> = tokens.into_iter().map(|token| token.span).collect();
+        assert_eq!(
+            vec![
+                Span {
+                    start: 0,
+                    end: 6,
+                    line: 1,
+                    column: 1
+                },
+                Span {
+                    start: 16,
+                    end: 21,
+                    line: 1,
+                    column: 16
+                },
+                Span {
+                    start: 24,
+                    end: 36,
+                    line: 2,
+                    column: 3
+                },
+                Span {
+                    start: 37,
+                    end: 38,
+                    line: 3,
+                    column: 7
+                },
+            ],
+            spans
+        );
+    }
+
+    #[test]
+    pub fn test_end_of_query_span() {
+        assert_eq!(
+            Span {
+                start: 9,
+                end: 9,
+                line: 2,
+                column: 3
+            },
+            Span::end_of("CREATE\nTA")
+        );
+    }
+
+    #[test]
+    pub fn test_operators() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("= <> != < <= > >= + - * / % || .").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Equals,
+                Token::NotEquals,
+                Token::NotEquals,
+                Token::LessThan,
+                Token::LessOrEqual,
+                Token::GreaterThan,
+                Token::GreaterOrEqual,
+                Token::Plus,
+                Token::Minus,
+                Token::Asterisk,
+                Token::Slash,
+                Token::Percent,
+                Token::Concat,
+                Token::Dot
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_expression_keywords() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("not AND Or is NULL between in TRUE false island").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Not,
+                Token::And,
+                Token::Or,
+                Token::Is,
+                Token::Null,
+                Token::Between,
+                Token::In,
+                Token::True,
+                Token::False,
+                Token::Identifier(String::from("island"))
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_longest_match() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("a<=b<>c>=-1.5e2||t.col").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::Identifier(String::from("a")),
+                Token::LessOrEqual,
+                Token::Identifier(String::from("b")),
+                Token::NotEquals,
+                Token::Identifier(String::from("c")),
+                Token::GreaterOrEqual,
+                Token::Minus,
+                Token::Scientific(1.5e2),
+                Token::Concat,
+                Token::Identifier(String::from("t")),
+                Token::Dot,
+                Token::Identifier(String::from("col"))
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn test_tie_prefers_first_parser() {
+        let tokenizer = Tokenizer::new();
+
+        // `E'x'` is a longer match as a string literal than the identifier `E`
+        assert_eq!(vec![Token::StringLiteral(String::from("x"))], tokenizer.tokenize("E'x'").unwrap());
+        assert_eq!(vec![Token::Create], tokenizer.tokenize("create").unwrap());
+        assert_eq!(vec![Token::Decimal(0.5)], tokenizer.tokenize(".5").unwrap());
+        assert_eq!(
+            vec![Token::Integer(10), Token::Minus, Token::Integer(2)],
+            tokenizer.tokenize("10-2").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_placeholders() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("(?, $1, $12, :name, :_x1)").unwrap();
+
+        assert_eq!(
+            vec![
+                Token::OpenBracket,
+                Token::Placeholder(Placeholder::Anonymous),
+                Token::Comma,
+                Token::Placeholder(Placeholder::Numbered(1)),
+                Token::Comma,
+                Token::Placeholder(Placeholder::Numbered(12)),
+                Token::Comma,
+                Token::Placeholder(Placeholder::Named(String::from("name"))),
+                Token::Comma,
+                Token::Placeholder(Placeholder::Named(String::from("_x1"))),
+                Token::CloseBracket
+            ],
+            tokens
+        );
+        assert!(tokenizer.tokenize("$0").is_err());
+        assert!(tokenizer.tokenize(": name").is_err());
+    }
+
+    #[test]
+    pub fn test_tokens_are_lazy() {
+        let tokenizer = Tokenizer::new();
+        let mut tokens = tokenizer.tokens("CREATE TABLE # never reached");
+
+        assert_eq!(Token::Create, tokens.next().unwrap().unwrap().token);
+        assert_eq!(Token::Table, tokens.next().unwrap().unwrap().token);
+        assert!(tokens.next().unwrap().is_err());
+        assert!(tokens.next().is_none());
+    }
+
+    /// Timing depends on the machine and build, so this only runs when asked for:
+    /// `cargo test --release -- --ignored test_tokenize_large_script_in_linear_time`
+    #[test]
+    #[ignore]
+    pub fn test_tokenize_large_script_in_linear_time() {
+        let tokenizer = Tokenizer::new();
+        let statement = "CREATE TABLE users_1 (name 'it''s', \"Age\" 42, score 3.5e2); /* block */ -- line\n";
+        let small_script = statement.repeat(1024 * 1024 / statement.len());
+        let large_script = statement.repeat(10 * 1024 * 1024 / statement.len());
+
+        let start = Instant::now();
+        let small_count = tokenizer
+            .tokens(&small_script)
+            .try_fold(0, |count, token| token.map(|_| count + 1))
+            .unwrap();
+        let small_time = start.elapsed();
+
+        let start = Instant::now();
+        let large_count = tokenizer
+            .tokens(&large_script)
+            .try_fold(0, |count, token| token.map(|_| count + 1))
+            .unwrap();
+        let large_time = start.elapsed();
+
+        assert!(large_count >= small_count * 10);
+        // Ten times the input should take roughly ten times as long, where a quadratic tokenizer would take a hundred
+        assert!(
+            large_time < small_time * 30,
+            "1 MB took {:?}, 10 MB took {:?}",
+            small_time,
+            large_time
+        );
     }
 }
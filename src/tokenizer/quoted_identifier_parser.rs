@@ -0,0 +1,103 @@
+use crate::errors::{DbErr, SyntaxError};
+
+use super::{Span, Token, TokenParser};
+
+/// Parses `"double quoted"` and `` `backtick` `` identifiers. They are returned exactly as written,
+/// so they keep their case, may contain spaces, and are never mapped onto keywords.
+/// The closing quote character is escaped by doubling it.
+pub(crate) struct QuotedIdentifierParser;
+
+impl QuotedIdentifierParser {
+    pub(crate) fn new() -> QuotedIdentifierParser {
+        QuotedIdentifierParser
+    }
+}
+
+impl TokenParser for QuotedIdentifierParser {
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr> {
+        let quote = match input.chars().next() {
+            Some(c) if c == '"' || c == '`' => c,
+            _ => return Ok(None),
+        };
+
+        let mut name = String::new();
+        let mut chars = input[1..].char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            if c != quote {
+                name.push(c);
+            } else if chars.peek().is_some_and(|(_, next)| *next == quote) {
+                chars.next();
+                name.push(quote);
+            } else if name.is_empty() {
+                return Err(DbErr::Generic(String::from("Quoted identifier can't be empty")));
+            } else {
+                return Ok(Some((Token::Identifier(name), idx + 2)));
+            }
+        }
+
+        Err(DbErr::Syntax(
+            SyntaxError::new(String::from("Unterminated quoted identifier"), Span::leading(1)).incomplete(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::{DbErr, SyntaxError},
+        tokenizer::{quoted_identifier_parser::QuotedIdentifierParser, Span, Token, TokenParser},
+    };
+
+    fn identifier(name: &str) -> Token {
+        Token::Identifier(String::from(name))
+    }
+
+    fn parse(input: &str) -> Token {
+        QuotedIdentifierParser::new().parse(input).unwrap().unwrap().0
+    }
+
+    #[test]
+    fn double_quoted() {
+        assert_eq!(
+            Some((identifier("Order Date"), 12)),
+            QuotedIdentifierParser::new().parse("\"Order Date\" INTEGER").unwrap()
+        );
+    }
+
+    #[test]
+    fn backtick_quoted() {
+        assert_eq!(identifier("MixedCase"), parse("`MixedCase`"));
+        assert_eq!(identifier("table"), parse("`table`"));
+    }
+
+    #[test]
+    fn doubled_quotes() {
+        assert_eq!(
+            Some((identifier("say \"hi\""), 12)),
+            QuotedIdentifierParser::new().parse("\"say \"\"hi\"\"\"").unwrap()
+        );
+        assert_eq!(identifier("a`b"), parse("`a``b`"));
+        assert_eq!(identifier("a\"b"), parse("`a\"b`"));
+    }
+
+    #[test]
+    fn no_matches() {
+        let parser = QuotedIdentifierParser::new();
+
+        assert_eq!(None, parser.parse("table").unwrap());
+        assert_eq!(None, parser.parse("'string'").unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        let parser = QuotedIdentifierParser::new();
+
+        assert_eq!(
+            DbErr::Syntax(SyntaxError::new(String::from("Unterminated quoted identifier"), Span::leading(1)).incomplete()),
+            parser.parse("\"unterminated").unwrap_err()
+        );
+        assert!(parser.parse("`a``").is_err());
+        assert!(parser.parse("\"\"").is_err());
+    }
+}
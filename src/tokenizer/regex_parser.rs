@@ -1 +1,170 @@
+use super::{Placeholder, Span, Token, TokenParser};
+use crate::errors::{DbErr, SyntaxError};
+use regex::Regex;
 
+/// Matches a regex at the start of the input and hands the matched text to `factory`,
+/// which decides the resulting token. Returning `None` from the factory rejects the match
+/// (e.g. `$0`, as parameters are numbered from one).
+pub(crate) struct RegexParser {
+    regex: Regex,
+    factory: fn(&str) -> Option<Token>,
+    /// Reported when the factory rejects a match, instead of leaving the text to the other parsers
+    rejection: Option<&'static str>,
+}
+
+impl RegexParser {
+    pub(crate) fn new(pattern: &str, factory: fn(&str) -> Option<Token>) -> RegexParser {
+        RegexParser {
+            regex: Regex::new(&format!("^(?:{})", pattern)).unwrap(),
+            factory,
+            rejection: None,
+        }
+    }
+
+    /// Makes a rejected match a syntax error with `message`, pointing at the matched text
+    pub(crate) fn rejected_with(mut self, message: &'static str) -> RegexParser {
+        self.rejection = Some(message);
+        self
+    }
+
+    /// `X'0AFF'`, two hex digits for every byte
+    pub(crate) fn blob() -> RegexParser {
+        RegexParser::new(r"[xX]'(?:[0-9a-fA-F]{2})*'", |text| {
+            let hex = &text[2..text.len() - 1];
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap());
+            Some(Token::BlobLiteral(bytes.collect()))
+        })
+    }
+
+    pub(crate) fn hex_integer() -> RegexParser {
+        RegexParser::new(r"0[xX][0-9a-fA-F]+", |text| {
+            i64::from_str_radix(&text[2..], 16).ok().map(Token::Integer)
+        })
+        .rejected_with("Integer literal out of range")
+    }
+
+    pub(crate) fn scientific() -> RegexParser {
+        RegexParser::new(r"(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)[eE][+-]?[0-9]+", |text| {
+            text.parse::<f64>().ok().map(Token::Scientific)
+        })
+    }
+
+    pub(crate) fn decimal() -> RegexParser {
+        RegexParser::new(r"[0-9]+\.[0-9]*|\.[0-9]+", |text| text.parse::<f64>().ok().map(Token::Decimal))
+    }
+
+    pub(crate) fn integer() -> RegexParser {
+        RegexParser::new(r"[0-9]+", |text| text.parse::<i64>().ok().map(Token::Integer)).rejected_with("Integer literal out of range")
+    }
+
+    /// `$1`, parameters are numbered from one
+    pub(crate) fn numbered_placeholder() -> RegexParser {
+        RegexParser::new(r"\$[0-9]+", |text| match text[1..].parse::<usize>() {
+            Ok(0) | Err(_) => None,
+            Ok(number) => Some(Token::Placeholder(Placeholder::Numbered(number))),
+        })
+    }
+
+    pub(crate) fn named_placeholder() -> RegexParser {
+        RegexParser::new(r":[a-zA-Z_][a-zA-Z0-9_]*", |text| {
+            Some(Token::Placeholder(Placeholder::Named(text[1..].to_owned())))
+        })
+    }
+}
+
+impl TokenParser for RegexParser {
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr> {
+        let Some(result) = self.regex.find(input) else {
+            return Ok(None);
+        };
+
+        match ((self.factory)(result.as_str()), self.rejection) {
+            (Some(token), _) => Ok(Some((token, result.end()))),
+            (None, Some(message)) => Err(DbErr::Syntax(SyntaxError::new(message.to_owned(), Span::leading(result.end())))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::DbErr,
+        tokenizer::{regex_parser::RegexParser, Placeholder, Span, Token, TokenParser},
+    };
+
+    #[test]
+    fn integers() {
+        assert_eq!(Some((Token::Integer(42), 2)), RegexParser::integer().parse("42 rest").unwrap());
+        assert_eq!(Some((Token::Integer(31), 4)), RegexParser::hex_integer().parse("0x1F").unwrap());
+        assert_eq!(Some((Token::Integer(255), 4)), RegexParser::hex_integer().parse("0XfF").unwrap());
+    }
+
+    #[test]
+    fn blobs() {
+        assert_eq!(
+            Some((Token::BlobLiteral(vec![10, 255]), 7)),
+            RegexParser::blob().parse("X'0aFF' rest").unwrap()
+        );
+        assert_eq!(Some((Token::BlobLiteral(Vec::new()), 3)), RegexParser::blob().parse("x''").unwrap());
+        assert_eq!(None, RegexParser::blob().parse("X'ABC'").unwrap());
+    }
+
+    #[test]
+    fn decimals() {
+        assert_eq!(Some((Token::Decimal(3.5), 3)), RegexParser::decimal().parse("3.5").unwrap());
+        assert_eq!(Some((Token::Decimal(0.25), 3)), RegexParser::decimal().parse(".25").unwrap());
+        assert_eq!(Some((Token::Decimal(7.0), 2)), RegexParser::decimal().parse("7.").unwrap());
+    }
+
+    #[test]
+    fn scientific() {
+        assert_eq!(Some((Token::Scientific(1e10), 4)), RegexParser::scientific().parse("1e10").unwrap());
+        assert_eq!(
+            Some((Token::Scientific(2.5e-3), 6)),
+            RegexParser::scientific().parse("2.5E-3").unwrap()
+        );
+        assert_eq!(
+            Some((Token::Scientific(0.5e+2), 5)),
+            RegexParser::scientific().parse(".5e+2").unwrap()
+        );
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            Some((Token::Placeholder(Placeholder::Numbered(3)), 2)),
+            RegexParser::numbered_placeholder().parse("$3,").unwrap()
+        );
+        assert_eq!(
+            Some((Token::Placeholder(Placeholder::Named(String::from("user_id"))), 8)),
+            RegexParser::named_placeholder().parse(":user_id)").unwrap()
+        );
+        assert_eq!(None, RegexParser::numbered_placeholder().parse("$0").unwrap());
+        assert_eq!(None, RegexParser::named_placeholder().parse(":1").unwrap());
+    }
+
+    #[test]
+    fn no_matches() {
+        assert_eq!(None, RegexParser::integer().parse("abc").unwrap());
+        assert_eq!(None, RegexParser::integer().parse("abc 12").unwrap());
+        assert_eq!(None, RegexParser::hex_integer().parse("0x").unwrap());
+        assert_eq!(None, RegexParser::scientific().parse("1e").unwrap());
+        assert_eq!(None, RegexParser::decimal().parse("12").unwrap());
+    }
+
+    #[test]
+    fn rejected_by_factory() {
+        assert_eq!(None, RegexParser::numbered_placeholder().parse("$0").unwrap());
+
+        match RegexParser::integer().parse("99999999999999999999 rest").unwrap_err() {
+            DbErr::Syntax(err) => {
+                assert_eq!("Integer literal out of range", err.message);
+                assert_eq!(Span::leading(20), err.span);
+            }
+            err => panic!("Expected a syntax error, got {:?}", err),
+        }
+    }
+}
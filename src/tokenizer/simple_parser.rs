@@ -1,4 +1,5 @@
 use super::{Token, TokenParser};
+use crate::errors::DbErr;
 
 pub(crate) struct SimpleParser {
     search_string: String,
@@ -15,12 +16,16 @@ impl SimpleParser {
 }
 
 impl TokenParser for SimpleParser {
-    fn parse_skip(&self, input: &mut String) -> Option<Token> {
-        if input.to_lowercase().starts_with(&self.search_string) {
-            input.replace_range(0..self.search_string.len(), "");
-            Some(self.result_token.clone())
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr> {
+        let len = self.search_string.len();
+
+        if input
+            .get(..len)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&self.search_string))
+        {
+            Ok(Some((self.result_token.clone(), len)))
         } else {
-            None
+            Ok(None)
         }
     }
 }
@@ -33,25 +38,18 @@ mod tests {
     fn match_create() {
         let parser = SimpleParser::new(String::from("create"), Token::Create);
 
-        let mut test1 = String::from("create EGGS");
-        let mut test2 = String::from("CREATE something");
-        let mut test3 = String::from("CREATE");
-
-        assert_eq!(Some(Token::Create), parser.parse_skip(&mut test1));
-        assert_eq!(Some(Token::Create), parser.parse_skip(&mut test2));
-        assert_eq!(Some(Token::Create), parser.parse_skip(&mut test3));
+        assert_eq!(Some((Token::Create, 6)), parser.parse("create EGGS").unwrap());
+        assert_eq!(Some((Token::Create, 6)), parser.parse("CREATE something").unwrap());
+        assert_eq!(Some((Token::Create, 6)), parser.parse("CREATE").unwrap());
     }
 
     #[test]
     fn no_matches() {
         let parser = SimpleParser::new(String::from("create"), Token::Create);
 
-        let mut test1 = String::from("12create");
-        let mut test2 = String::from("12create12");
-        let mut test3 = String::from("12CREATE1");
-
-        assert_eq!(None, parser.parse_skip(&mut test1));
-        assert_eq!(None, parser.parse_skip(&mut test2));
-        assert_eq!(None, parser.parse_skip(&mut test3));
+        assert_eq!(None, parser.parse("12create").unwrap());
+        assert_eq!(None, parser.parse("12create12").unwrap());
+        assert_eq!(None, parser.parse("12CREATE1").unwrap());
+        assert_eq!(None, parser.parse("cré").unwrap());
     }
 }
@@ -0,0 +1,130 @@
+use crate::errors::{DbErr, SyntaxError};
+
+use super::{Span, Token, TokenParser};
+
+/// Parses single quoted string literals. A quote inside the literal is written by doubling it (`'it''s'`),
+/// and literals prefixed with `E` (`E'line\n'`) additionally understand backslash escapes.
+pub(crate) struct StringParser;
+
+impl StringParser {
+    pub(crate) fn new() -> StringParser {
+        StringParser
+    }
+
+    fn unescape(escaped: char) -> char {
+        match escaped {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            '0' => '\0',
+            other => other,
+        }
+    }
+}
+
+impl TokenParser for StringParser {
+    fn parse(&self, input: &str) -> Result<Option<(Token, usize)>, DbErr> {
+        let (allow_escapes, prefix_len) = if input.starts_with('\'') {
+            (false, 1)
+        } else if input.starts_with("e'") || input.starts_with("E'") {
+            (true, 2)
+        } else {
+            return Ok(None);
+        };
+
+        let mut value = String::new();
+        let mut chars = input[prefix_len..].char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\'' => {
+                    if let Some((_, '\'')) = chars.peek() {
+                        chars.next();
+                        value.push('\'');
+                    } else {
+                        return Ok(Some((Token::StringLiteral(value), prefix_len + idx + 1)));
+                    }
+                }
+                '\\' if allow_escapes => match chars.next() {
+                    Some((_, escaped)) => value.push(StringParser::unescape(escaped)),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+
+        Err(DbErr::Syntax(
+            SyntaxError::new(String::from("Unterminated string literal"), Span::leading(prefix_len)).incomplete(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::{DbErr, SyntaxError},
+        tokenizer::{string_parser::StringParser, Span, Token, TokenParser},
+    };
+
+    fn literal(value: &str) -> Token {
+        Token::StringLiteral(String::from(value))
+    }
+
+    fn parse(input: &str) -> Token {
+        StringParser::new().parse(input).unwrap().unwrap().0
+    }
+
+    #[test]
+    fn simple_string() {
+        assert_eq!(
+            Some((literal("hello world"), 13)),
+            StringParser::new().parse("'hello world' rest").unwrap()
+        );
+    }
+
+    #[test]
+    fn doubled_quotes() {
+        assert_eq!(literal("it's"), parse("'it''s'"));
+        assert_eq!(literal(""), parse("''"));
+        assert_eq!(literal("'"), parse("''''"));
+    }
+
+    #[test]
+    fn backslashes_only_escape_in_e_strings() {
+        assert_eq!(literal("a\\nb"), parse("'a\\nb'"));
+        assert_eq!(literal("a\nb"), parse("E'a\\nb'"));
+        assert_eq!(literal("tab\there"), parse("e'tab\\there'"));
+        assert_eq!(literal("it's"), parse("E'it\\'s'"));
+    }
+
+    #[test]
+    fn multi_line() {
+        assert_eq!(
+            Some((literal("first\nsecond"), 14)),
+            StringParser::new().parse("'first\nsecond'").unwrap()
+        );
+    }
+
+    #[test]
+    fn no_matches() {
+        assert_eq!(None, StringParser::new().parse("egg 'value'").unwrap());
+    }
+
+    #[test]
+    fn unterminated() {
+        let parser = StringParser::new();
+
+        assert_eq!(
+            DbErr::Syntax(SyntaxError::new(String::from("Unterminated string literal"), Span::leading(1)).incomplete()),
+            parser.parse("'abc").unwrap_err()
+        );
+        assert_eq!(
+            DbErr::Syntax(SyntaxError::new(String::from("Unterminated string literal"), Span::leading(2)).incomplete()),
+            parser.parse("E'abc").unwrap_err()
+        );
+        assert!(parser.parse("'it''").is_err());
+        assert!(parser.parse("E'abc\\'").is_err());
+    }
+}